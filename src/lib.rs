@@ -0,0 +1,583 @@
+mod profile;
+
+pub use profile::color::Color;
+pub use profile::detect::detect_format;
+pub use profile::encoding::{decode, decode_with, encode, encoding_for_label, guess_encoding, DecodePolicy, Decoded,
+                            EncodingSource, LineEnding, OutputEncoding};
+pub use profile::error::{Location, SchemeError};
+pub use profile::format::{Capabilities, FormatInfo, SchemeFormat, SchemeReader, SchemeWriter, FORMATS};
+pub use profile::generic::{ColorScheme, ColorSchemes};
+pub use profile::select::SchemePattern;
+#[cfg(test)]
+use std::io::{self, Read};
+
+/// Convert the content of a scheme file from one format to another
+pub fn convert(input: &[u8], scheme_from: SchemeFormat, scheme_to: SchemeFormat) -> Result<String, SchemeError> {
+    // Convert str to ColorSchemes
+    let gcs = ColorSchemes::from_literal(decode(input)?.as_str(), scheme_from)?;
+    // ColorSchemes to str
+    gcs.to_literal(scheme_to)
+}
+
+/// Convert a scheme file and merge the result into an existing Windows Terminal settings.json
+pub fn merge_wt(input: &[u8], scheme_from: SchemeFormat, settings: &[u8], profile: Option<&str>) -> Result<String, SchemeError> {
+    let gcs = ColorSchemes::from_literal(decode(input)?.as_str(), scheme_from)?;
+    gcs.merge_wt(decode(settings)?.as_str(), profile)
+}
+
+#[test]
+fn test_ground() {
+    let f = std::fs::File::open("test/alacritty.yml").unwrap();
+    let mut reader = io::BufReader::new(f);
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf).unwrap();
+}
+
+#[test]
+fn test_securecrt_roundtrip() {
+    let src = std::fs::read("test/settings.json").unwrap();
+    // SecureCRT sessions hold one palette
+    assert!(matches!(convert(src.as_slice(), SchemeFormat::WindowsTerminal, SchemeFormat::SecureCRT),
+        Err(SchemeError::MultipleSchemes { format: SchemeFormat::SecureCRT, .. })));
+    let schemes = ColorSchemes::from_wt(&decode(src.as_slice()).unwrap()).unwrap();
+    let files = schemes.to_literal_per_scheme(SchemeFormat::SecureCRT);
+    for (schm, (_, crt)) in schemes.schemes().iter().zip(files) {
+        assert!(crt.starts_with(&format!("S:\"Color Scheme\"={}\nB:\"ANSI Color RGB\"=00000040\n", schm.name)));
+        let back = ColorSchemes::from_securecrt(&crt).unwrap();
+        let back = &back.schemes()[0];
+        // The foreground and background of a session come from its color scheme
+        assert_eq!((&back.name, back.ansi(), back.foreground, back.background),
+                   (&schm.name, schm.ansi(), schm.white, schm.black));
+    }
+
+    // `Color Schemes.ini` holds several foreground/background pairs and no ANSI colors
+    let ini = schemes.to_literal(SchemeFormat::SecureCRTSchemes).unwrap();
+    assert!(ini.starts_with("B:\"Blazer\"=00000008\n d9 e6 f2 00 0d 19 26 00\nB:\"Campbell\"=00000008\n"));
+    assert!(!ini.contains("ANSI Color RGB"));
+    assert!(matches!(ColorSchemes::from_securecrt(&ini),
+        Err(SchemeError::MissingKey { path, scheme: Some(scheme), .. }) if path == "ANSI Color RGB" && scheme == "Blazer"));
+    let quoted = ColorSchemes::new(vec![ColorScheme { name: "\"Quoted\"".to_string(), ..schemes.schemes()[0].clone() }]);
+    assert!(quoted.to_securecrt_schemes().starts_with("B:\"'Quoted'\"=00000008\n"));
+
+    // Named pairs pasted after a session palette use it
+    let session = schemes.to_literal_per_scheme(SchemeFormat::SecureCRT).remove(1).1;
+    let both = format!("{}B:\"Solarized\"=00000008\n 83 94 96 00 00 2b 36 00\n", session);
+    let both = ColorSchemes::from_securecrt(&both).unwrap();
+    assert_eq!(both.schemes().len(), 1);
+    assert_eq!(both.schemes()[0].name, "Solarized");
+    assert_eq!(both.schemes()[0].ansi(), schemes.schemes()[1].ansi());
+    assert_eq!(both.schemes()[0].background, Color::rgb(0x00, 0x2b, 0x36));
+
+    let truncated = "B:\"Solarized\"=00000004\n 83 94 96 00\n";
+    assert!(matches!(ColorSchemes::from_securecrt(truncated), Err(SchemeError::InvalidValue { .. })));
+    // A corrupt length is a syntax error, not an allocation failure
+    let huge = "B:\"ANSI Color RGB\"=7fffffffffffffff\n 00 00 00 00\n";
+    assert!(matches!(ColorSchemes::from_securecrt(huge), Err(SchemeError::Syntax { .. })));
+    assert!(matches!(detect_format(huge), Err(SchemeError::Syntax { .. })));
+}
+
+#[cfg(test)]
+fn read_fixture(path: &str, fmt: SchemeFormat) -> ColorScheme {
+    let src = decode(std::fs::read(path).unwrap().as_slice()).unwrap();
+    assert_eq!(detect_format(&src).unwrap().0, fmt, "{}", path);
+    let schemes = ColorSchemes::from_literal(&src, fmt).unwrap();
+    assert_eq!(schemes.schemes().len(), 1, "{}", path);
+    schemes.schemes()[0].clone()
+}
+
+#[test]
+fn test_mobaxterm_ini() {
+    let schm = read_fixture("test/MobaXterm.ini", SchemeFormat::MobaXTerm);
+    assert_eq!(schm.background, Color::rgb(30, 30, 30));
+    assert_eq!(schm.bright_blue, Color::rgb(72, 129, 255));
+    assert_eq!(schm.cursor, Some(Color::rgb(180, 180, 192)));
+    assert_eq!(schm.bold, None);
+}
+
+#[test]
+fn test_alacritty_toml_config() {
+    let schm = read_fixture("test/alacritty.toml", SchemeFormat::AlacrittyToml);
+    assert_eq!(schm.foreground, Color::rgb(0xeb, 0xdb, 0xb2));
+    assert_eq!(schm.magenta, Color::rgb(0xb1, 0x62, 0x86));
+    assert_eq!(schm.bright_black, Color::rgb(0x92, 0x83, 0x74));
+    // `CellForeground` and `CellBackground` are no colors of their own
+    assert_eq!((schm.cursor, schm.cursor_text), (None, None));
+}
+
+#[test]
+fn test_kitty_theme() {
+    let schm = read_fixture("test/kitty.conf", SchemeFormat::Kitty);
+    assert_eq!(schm.name, "Dracula");
+    assert_eq!(schm.author.as_deref(), Some("Zeno Rocha"));
+    assert_eq!(schm.black, Color::rgb(0x21, 0x22, 0x2c));
+    assert_eq!(schm.bright_black, Color::rgb(0x62, 0x72, 0xa4));
+    assert_eq!(schm.selection_background, Some(Color::rgb(0x44, 0x47, 0x5a)));
+    assert_eq!(schm.cursor, Some(Color::rgb(0xf8, 0xf8, 0xf2)));
+    assert_eq!(schm.cursor_text, None);
+}
+
+#[test]
+fn test_wezterm_scheme() {
+    let schm = read_fixture("test/wezterm.toml", SchemeFormat::WezTerm);
+    assert_eq!(schm.name, "Tomorrow Night");
+    assert_eq!(schm.author.as_deref(), Some("Chris Kempson"));
+    assert_eq!(schm.bright_black, Color::rgb(0x96, 0x98, 0x96));
+    assert_eq!(schm.cursor_text, Some(Color::rgb(0x1d, 0x1f, 0x21)));
+    assert_eq!(schm.selection_background, Some(Color::rgb(0x37, 0x3b, 0x41)));
+}
+
+#[test]
+fn test_konsole_breeze() {
+    let schm = read_fixture("test/Breeze.colorscheme", SchemeFormat::Konsole);
+    assert_eq!(schm.name, "Breeze");
+    assert_eq!(schm.background, Color::rgb(35, 38, 39));
+    assert_eq!(schm.red, Color::rgb(237, 21, 21));
+    assert_eq!(schm.bright_red, Color::rgb(192, 57, 43));
+    assert_eq!(schm.bold, Some(Color::rgb(255, 255, 255)));
+}
+
+#[test]
+fn test_xresources_variants() {
+    let src = "! Solarized
+#define S_base03 #002b36
+#define S_base0 #839496
+#define S_back S_base03
+URxvt.background: S_back
+XTerm*foreground: S_base0
+*color0: rgb:07/36/42
+*color1: rgb:dc/32/2f
+*color2: rgb:85/99/00
+*color3: rgb:b5/89/00
+*color4: rgb:26/8b/d2
+*color5: rgb:d3/36/82
+*color6: rgb:2a/a1/98
+*color7: rgb:eeee/e8e8/d5d5
+*color8: #002b36
+*color9: #cb4b16
+*color10: #586e75
+*color11: #657b83
+*color12: #839496
+*color13: #6c71c4
+*color14: #93a1a1
+*color15: #fdf6e3
+";
+    let xres = convert(src.as_bytes(), SchemeFormat::Xresources, SchemeFormat::Xresources).unwrap();
+    assert!(xres.contains("*.background: #002B36"));
+    assert!(xres.contains("*.foreground: #839496"));
+    assert!(xres.contains("*.color0: #073642"));
+    assert!(xres.contains("*.color7: #EEE8D5"));
+}
+
+#[test]
+fn test_gnome_terminal_dump() {
+    // Profiles following the system theme are skipped
+    let schm = read_fixture("test/gnome-terminal.dconf", SchemeFormat::GnomeTerminal);
+    assert_eq!(schm.name, "Tango Dark");
+    assert_eq!(schm.bright_yellow, Color::rgb(252, 233, 79));
+    assert_eq!(schm.foreground, Color::rgb(211, 215, 207));
+    // Switched off by `cursor-colors-set` and `bold-color-same-as-fg`
+    assert_eq!((schm.cursor, schm.bold), (None, None));
+    assert_eq!(schm.selection_background, Some(Color::rgb(114, 159, 207)));
+}
+
+#[test]
+fn test_wt_merge() {
+    let src = std::fs::read("test/alacritty.yml").unwrap();
+    let settings = std::fs::read("test/settings.json").unwrap();
+    let merged = merge_wt(src.as_slice(), SchemeFormat::Alacritty, settings.as_slice(), Some("Windows PowerShell")).unwrap();
+    let merged: serde_json::Value = serde_json::from_str(&merged).unwrap();
+    let original: serde_json::Value = serde_json::from_slice(&settings).unwrap();
+    assert_eq!(merged["actions"], original["actions"]);
+    assert_eq!(merged["profiles"]["defaults"], original["profiles"]["defaults"]);
+    assert_eq!(merged["profiles"]["list"][0]["colorScheme"], "default");
+    let schemes = merged["schemes"].as_array().unwrap();
+    assert_eq!(schemes.len(), original["schemes"].as_array().unwrap().len() + 1);
+    assert_eq!(schemes.last().unwrap()["background"], "#1D1F21");
+}
+
+#[test]
+fn test_detect_format() {
+    let src = std::fs::read("test/alacritty.yml").unwrap();
+    let detect = |fmt: SchemeFormat| {
+        let converted = convert(src.as_slice(), SchemeFormat::Alacritty, fmt).unwrap();
+        detect_format(&converted).unwrap().0
+    };
+    for fmt in [SchemeFormat::WindowsTerminal, SchemeFormat::SecureCRT, SchemeFormat::XShell,
+        SchemeFormat::Alacritty, SchemeFormat::AlacrittyToml, SchemeFormat::MobaXTerm, SchemeFormat::ITerm2,
+        SchemeFormat::Kitty, SchemeFormat::WezTerm, SchemeFormat::Konsole, SchemeFormat::Xresources,
+        SchemeFormat::GnomeTerminal] {
+        assert_eq!(detect(fmt), fmt);
+    }
+    assert_eq!(detect_format(decode(src.as_slice()).unwrap().as_str()).unwrap().0, SchemeFormat::Alacritty);
+    let settings = std::fs::read("test/settings.json").unwrap();
+    assert_eq!(detect_format(decode(settings.as_slice()).unwrap().as_str()).unwrap().0, SchemeFormat::WindowsTerminal);
+    let xcs = std::fs::read("test/ColorSchemes.xcs").unwrap();
+    assert_eq!(detect_format(decode(xcs.as_slice()).unwrap().as_str()).unwrap().0, SchemeFormat::XShell);
+    // A file that only looks like one format reports why its reader rejected it
+    let missing = decode(settings.as_slice()).unwrap().replacen("\"brightPurple\"", "\"brightPurpl\"", 1);
+    assert!(matches!(detect_format(&missing), Err(SchemeError::MissingKey { path, .. }) if path == "brightPurple"));
+    assert!(matches!(detect_format("nothing to see"), Err(SchemeError::Undetected)));
+}
+
+#[test]
+fn test_format_from_extension() {
+    assert_eq!(SchemeFormat::from_extension("Darcula.xcs").unwrap(), SchemeFormat::XShell);
+    assert_eq!(SchemeFormat::from_extension("dir.d/Darcula.ITERMCOLORS").unwrap(), SchemeFormat::ITerm2);
+    assert_eq!(SchemeFormat::from_extension("/home/me/.Xresources").unwrap(), SchemeFormat::Xresources);
+    // The names terminals give their own files, and the extensions --split writes
+    assert_eq!(SchemeFormat::from_extension("~/.config/alacritty/alacritty.toml").unwrap(), SchemeFormat::AlacrittyToml);
+    assert_eq!(SchemeFormat::from_extension("MobaXterm.ini").unwrap(), SchemeFormat::MobaXTerm);
+    assert_eq!(SchemeFormat::from_extension("Config/Color Schemes.ini").unwrap(), SchemeFormat::SecureCRTSchemes);
+    assert_eq!(SchemeFormat::from_extension("themes/Tango.dconf").unwrap(), SchemeFormat::GnomeTerminal);
+    assert_eq!(SchemeFormat::from_extension("themes/Tango.xresources").unwrap(), SchemeFormat::Xresources);
+    let ambiguous = SchemeFormat::from_extension("Tomorrow Night.toml").unwrap_err();
+    assert!(matches!(ambiguous, SchemeError::Ambiguous(_)));
+    // Messages name formats the way -t accepts them
+    assert!(ambiguous.to_string().contains("alacritty-toml, wezterm"), "{}", ambiguous);
+    for info in FORMATS {
+        assert_eq!(SchemeFormat::from_name(&info.format.to_string()).unwrap(), info.format);
+    }
+    assert!(matches!(SchemeFormat::from_extension("Darcula"), Err(SchemeError::UnknownFormat(_))));
+}
+
+#[test]
+fn test_scheme_errors() {
+    let settings = String::from_utf8(std::fs::read("test/settings.json").unwrap()).unwrap();
+    let missing = settings.replacen("\"brightPurple\"", "\"brightPurpl\"", 1);
+    match convert(missing.as_bytes(), SchemeFormat::WindowsTerminal, SchemeFormat::Kitty) {
+        Err(SchemeError::MissingKey { format: SchemeFormat::WindowsTerminal, scheme: Some(_), path }) => {
+            assert_eq!(path, "brightPurple");
+        }
+        other => { panic!("unexpected {:?}", other); }
+    }
+    let src = "colors:\n  primary:\n    background: '#1d1f21'\n";
+    match convert(src.as_bytes(), SchemeFormat::Alacritty, SchemeFormat::Kitty) {
+        Err(SchemeError::MissingKey { path, .. }) => { assert_eq!(path, "colors.normal.black"); }
+        other => { panic!("unexpected {:?}", other); }
+    }
+    let src = std::fs::read_to_string("test/alacritty.yml").unwrap().replace("'#1d1f21'", "'#abcde'");
+    assert!(matches!(convert(src.as_bytes(), SchemeFormat::Alacritty, SchemeFormat::Kitty),
+        Err(SchemeError::InvalidValue { .. })));
+    match convert(b"{\n  \"schemes\": [,]\n}", SchemeFormat::WindowsTerminal, SchemeFormat::Kitty) {
+        Err(e @ SchemeError::Syntax { location: Some(Location { line: 2, .. }), .. }) => {
+            assert_eq!(e.exit_code(), 4);
+            // The position is only given once
+            assert_eq!(e.to_string().matches("line 2").count(), 1, "{}", e);
+        }
+        other => { panic!("unexpected {:?}", other); }
+    }
+}
+
+#[test]
+fn test_extra_colors() {
+    let settings: serde_json::Value = serde_json::from_slice(&std::fs::read("test/settings.json").unwrap()).unwrap();
+    // One Half Light has cursor and selection colors different from its foreground
+    let scheme = settings["schemes"].as_array().unwrap().iter()
+        .find(|s| s["name"] == "One Half Light")
+        .unwrap();
+    let src = serde_json::json!({ "schemes": [scheme] }).to_string();
+    let wt = convert(src.as_bytes(), SchemeFormat::WindowsTerminal, SchemeFormat::WindowsTerminal).unwrap();
+    assert!(wt.contains("\"cursorColor\": \"#4F525D\""));
+    for fmt in [SchemeFormat::Alacritty, SchemeFormat::AlacrittyToml, SchemeFormat::ITerm2, SchemeFormat::Kitty,
+        SchemeFormat::WezTerm, SchemeFormat::GnomeTerminal] {
+        let converted = convert(src.as_bytes(), SchemeFormat::WindowsTerminal, fmt).unwrap();
+        let back = convert(converted.as_bytes(), fmt, SchemeFormat::WindowsTerminal).unwrap();
+        assert!(back.contains("\"cursorColor\": \"#4F525D\""), "{:?}", fmt);
+        assert!(back.contains("\"selectionBackground\": \"#FFFFFF\""), "{:?}", fmt);
+    }
+    let xcs = "[Bold]\ntext=c0c0c0\ntext(bold)=ffffff\nbackground=000000\n[Names]\nname0=Bold\ncount=1\n";
+    let xcs = convert(xcs.as_bytes(), SchemeFormat::XShell, SchemeFormat::XShell).unwrap();
+    assert!(xcs.contains("text(bold)=ffffff"));
+    // Without the extra colors, they are derived from the foreground
+    let yml = std::fs::read("test/alacritty.yml").unwrap();
+    let kitty = convert(yml.as_slice(), SchemeFormat::Alacritty, SchemeFormat::Kitty).unwrap();
+    assert!(kitty.contains("cursor #C5C8C6"));
+    assert!(kitty.contains("selection_background #C5C8C6"));
+    // Theme metadata survives formats that have it
+    let mut schemes = ColorSchemes::from_alacritty(&decode(yml.as_slice()).unwrap()).unwrap().into_vec();
+    schemes[0].author = Some("Chris Kempson".to_string());
+    let schemes = ColorSchemes::new(schemes);
+    let wezterm = ColorSchemes::from_wezterm(&schemes.to_wezterm()).unwrap();
+    assert_eq!(wezterm.schemes()[0].author.as_deref(), Some("Chris Kempson"));
+    let kitty = ColorSchemes::from_kitty(&wezterm.to_kitty()).unwrap();
+    assert_eq!(kitty.schemes()[0].author.as_deref(), Some("Chris Kempson"));
+}
+
+#[test]
+fn test_color_syntaxes() {
+    let red = Color::rgb(0xff, 0, 0);
+    for s in ["#f00", "#ff0000", "#FF0000ff", "0xff0000", "ff0000", "rgb(255, 0, 0)", "rgb:ffff/0000/0000",
+        "rgb:f/0/0", "255,0,0", "red", "Red"] {
+        assert_eq!(Color::parse(s), Some(red), "{}", s);
+    }
+    assert_eq!(Color::parse("rgba(255,0,0,0.5)"), Some(Color { a: 0x80, ..red }));
+    assert_eq!(Color::parse("#f008"), Some(Color { a: 0x88, ..red }));
+    assert_eq!(Color::from_f64(1.0, 0.0, 0.0), red);
+    for s in ["#abcde", "0xfff", "rgb(256,0,0)", "rgb:12345/0/0", "1,2", "CellForeground", ""] {
+        assert_eq!(Color::parse(s), None, "{}", s);
+    }
+    let c = Color::rgb(0x1d, 0x1f, 0x21);
+    assert_eq!(c.to_hex(), "#1D1F21");
+    assert_eq!(format!("{:x}", c), "1d1f21");
+    assert_eq!(format!("{:#x}", c), "#1d1f21");
+    assert_eq!(format!("{:X}", c), "1D1F21");
+    assert_eq!(c.to_decimal(), "29,31,33");
+}
+
+#[test]
+fn test_format_registry() {
+    let src = std::fs::read("test/alacritty.yml").unwrap();
+    let schemes = ColorSchemes::from_literal(&decode(src.as_slice()).unwrap(), SchemeFormat::Alacritty).unwrap();
+    for info in FORMATS {
+        assert_eq!(info.format.info().name(), info.name());
+        for alias in info.aliases {
+            assert_eq!(SchemeFormat::from_name(&alias.to_uppercase()).unwrap(), info.format);
+        }
+        // Every registered writer produces something its reader accepts, except `Color Schemes.ini`
+        // which has no ANSI colors without a session
+        let written = info.writer.write(&schemes);
+        assert_eq!(info.reader.read(&written).is_ok(), info.format != SchemeFormat::SecureCRTSchemes, "{}", info.name());
+    }
+    assert!(matches!(SchemeFormat::from_name("vim"), Err(SchemeError::UnknownFormat(_))));
+}
+
+#[test]
+fn test_select_schemes() {
+    let settings = std::fs::read("test/settings.json").unwrap();
+    let schemes = || ColorSchemes::from_literal(&decode(settings.as_slice()).unwrap(), SchemeFormat::WindowsTerminal).unwrap();
+    let names = |schemes: &ColorSchemes| schemes.schemes().iter().map(|s| s.name.clone()).collect::<Vec<String>>();
+    let patterns = |p: &[&str]| p.iter().map(|p| SchemePattern::parse(p).unwrap()).collect::<Vec<SchemePattern>>();
+
+    let mut selected = schemes();
+    selected.retain_matching(&patterns(&["Campbell", "Solarized*"]), &patterns(&["/Light$/"]));
+    assert_eq!(names(&selected), ["Campbell", "Solarized Dark"]);
+    let mut selected = schemes();
+    selected.retain_matching(&patterns(&["/^Tango (Dark|Light)$/", "One Half ????"]), &[]);
+    assert_eq!(names(&selected), ["One Half Dark", "Tango Dark", "Tango Light"]);
+    let mut selected = schemes();
+    selected.retain_matching(&[], &patterns(&["*"]));
+    assert!(selected.schemes().is_empty());
+
+    let mut renamed = schemes();
+    renamed.rename("Campbell", "Campbell Custom").unwrap();
+    assert!(names(&renamed).contains(&"Campbell Custom".to_string()));
+    assert!(matches!(renamed.rename("Campbell", "Again"), Err(SchemeError::NoSuchScheme(_))));
+    assert!(matches!(SchemePattern::parse("/(/"), Err(SchemeError::InvalidArgument { .. })));
+}
+
+#[test]
+fn test_alacritty_roundtrip() {
+    let src = std::fs::read("test/alacritty.yml").unwrap();
+    let yml = convert(src.as_slice(), SchemeFormat::Alacritty, SchemeFormat::Alacritty).unwrap();
+    assert!(yml.contains("colors:"));
+    let wt = convert(yml.as_bytes(), SchemeFormat::Alacritty, SchemeFormat::WindowsTerminal).unwrap();
+    assert_eq!(wt, convert(src.as_slice(), SchemeFormat::Alacritty, SchemeFormat::WindowsTerminal).unwrap());
+}
+
+#[test]
+fn test_roundtrip_all_pairs() {
+    let src = std::fs::read("test/alacritty.yml").unwrap();
+    let expected = convert(src.as_slice(), SchemeFormat::Alacritty, SchemeFormat::WindowsTerminal).unwrap();
+    // `Color Schemes.ini` cannot be read back on its own
+    let formats: Vec<&FormatInfo> = FORMATS.iter().filter(|info| info.format != SchemeFormat::SecureCRTSchemes).collect();
+    for from in formats.iter() {
+        let first = convert(src.as_slice(), SchemeFormat::Alacritty, from.format).unwrap();
+        for to in formats.iter() {
+            let second = convert(first.as_bytes(), from.format, to.format)
+                .unwrap_or_else(|e| panic!("{} -> {}: {}", from.name(), to.name(), e));
+            let wt = convert(second.as_bytes(), to.format, SchemeFormat::WindowsTerminal)
+                .unwrap_or_else(|e| panic!("{} -> {} -> wt: {}", from.name(), to.name(), e));
+            assert_eq!(wt, expected, "{} -> {}", from.name(), to.name());
+        }
+    }
+}
+
+#[test]
+fn test_single_scheme_formats() {
+    let settings = std::fs::read("test/settings.json").unwrap();
+    match convert(settings.as_slice(), SchemeFormat::WindowsTerminal, SchemeFormat::Kitty) {
+        Err(SchemeError::MultipleSchemes { format: SchemeFormat::Kitty, count: 13 }) => {}
+        other => { panic!("unexpected {:?}", other); }
+    }
+    let mut schemes = ColorSchemes::from_literal(&decode(settings.as_slice()).unwrap(), SchemeFormat::WindowsTerminal).unwrap();
+    let files = schemes.to_literal_per_scheme(SchemeFormat::Kitty);
+    assert_eq!(files.len(), 13);
+    assert_eq!(files[0].0, "Blazer.conf");
+    for (_, content) in files.iter() {
+        assert!(ColorSchemes::from_literal(content, SchemeFormat::Kitty).is_ok());
+    }
+    schemes.rename("Campbell", "Tango/Dark").unwrap();
+    schemes.rename("Campbell Powershell", "Tango_Dark").unwrap();
+    let names: Vec<String> = schemes.to_literal_per_scheme(SchemeFormat::GnomeTerminal).into_iter().map(|(n, _)| n).collect();
+    assert!(names.contains(&"Tango_Dark.dconf".to_string()));
+    assert!(names.contains(&"Tango_Dark-2.dconf".to_string()));
+
+    assert!(matches!(schemes.pick("Campbell"), Err(SchemeError::NoSuchScheme(_))));
+    schemes.pick("Vintage").unwrap();
+    assert!(schemes.to_literal(SchemeFormat::Kitty).unwrap().contains("## name: Vintage"));
+}
+
+#[test]
+fn test_output_encoding() {
+    let xcs = std::fs::read("test/ColorSchemes.xcs").unwrap();
+    let info = SchemeFormat::XShell.info();
+    let converted = convert(xcs.as_slice(), SchemeFormat::XShell, SchemeFormat::XShell).unwrap();
+    let encoded = encode(&converted, info.encoding, info.line_ending);
+    // Same BOM and line endings as the files XShell exports
+    assert_eq!(&encoded[..2], &xcs[..2]);
+    assert_eq!(&encoded[..2], &[0xff, 0xfe]);
+    assert!(encoded.windows(4).any(|w| w == [b'\r', 0, b'\n', 0]));
+    let wt = convert(encoded.as_slice(), SchemeFormat::XShell, SchemeFormat::WindowsTerminal).unwrap();
+    assert_eq!(wt, convert(xcs.as_slice(), SchemeFormat::XShell, SchemeFormat::WindowsTerminal).unwrap());
+
+    assert_eq!(encode("a\r\nb\n", OutputEncoding::Utf8, LineEnding::Lf), b"a\nb\n");
+    assert_eq!(encode("a\nb", OutputEncoding::Utf8Bom, LineEnding::CrLf), b"\xef\xbb\xbfa\r\nb");
+    assert_eq!(encode("a", OutputEncoding::Utf16BeBom, LineEnding::Lf), [0xfe, 0xff, 0, b'a']);
+    let be = encode(&converted, OutputEncoding::Utf16BeBom, LineEnding::CrLf);
+    assert!(convert(be.as_slice(), SchemeFormat::XShell, SchemeFormat::WindowsTerminal).is_ok());
+}
+
+#[test]
+fn test_input_decoding() {
+    let xcs = std::fs::read("test/ColorSchemes.xcs").unwrap();
+    let decoded = decode_with(xcs.as_slice(), None, DecodePolicy::Strict).unwrap();
+    assert_eq!(decoded.encoding, encoding_rs::UTF_16LE);
+    assert_eq!(decoded.source, EncodingSource::Bom);
+    assert!(decoded.text.starts_with('['));
+    let forced = decode_with(xcs.as_slice(), Some(encoding_for_label("UTF-16LE").unwrap()), DecodePolicy::Strict).unwrap();
+    assert_eq!(forced.source, EncodingSource::Forced);
+    assert_eq!(forced.text, decoded.text);
+
+    let invalid = b"[Colors]\nBlack=0,0,0\n\xff\xfe\xfd";
+    let utf8 = Some(encoding_rs::UTF_8);
+    match decode_with(invalid, utf8, DecodePolicy::Strict) {
+        Err(SchemeError::Decode { offset, .. }) => { assert_eq!(offset, 21); }
+        other => { panic!("unexpected {:?}", other); }
+    }
+    let replaced = decode_with(invalid, utf8, DecodePolicy::Replace).unwrap();
+    assert!(replaced.replaced);
+    assert!(replaced.text.ends_with('\u{fffd}'));
+    let latin1 = decode_with(invalid, Some(encoding_for_label("windows-1252").unwrap()), DecodePolicy::Strict).unwrap();
+    assert!(latin1.text.ends_with("ÿþý"));
+    assert!(matches!(encoding_for_label("klingon"), Err(SchemeError::InvalidArgument { .. })));
+}
+
+#[test]
+fn test_xshell_roundtrip() {
+    let xcs = std::fs::read("test/ColorSchemes.xcs").unwrap();
+    let info = SchemeFormat::XShell.info();
+    let converted = convert(xcs.as_slice(), SchemeFormat::XShell, SchemeFormat::XShell).unwrap();
+    assert_eq!(encode(&converted, info.encoding, info.line_ending), xcs);
+
+    // `Names` decides which sections are schemes and in which order
+    let xcs = "[B]\ntext=ffffff\n[A]\ntext=000000\n[Unlisted]\ntext=808080\n[Names]\nname0=B\nname1=A\ncount=2\n";
+    let schemes = ColorSchemes::from_xshell(xcs).unwrap();
+    let names: Vec<&str> = schemes.schemes().iter().map(|schm| schm.name.as_str()).collect();
+    assert_eq!(names, ["A", "B"]);
+    // Falls back to every section when `Names` is incomplete
+    let xcs = "[B]\ntext=ffffff\n[A]\ntext=000000\n[Names]\nname0=B\ncount=2\n";
+    assert_eq!(ColorSchemes::from_xshell(xcs).unwrap().schemes().len(), 2);
+    let xcs = "[B]\ntext=ffffff\n[Names]\nname0=A\ncount=1\n";
+    assert!(matches!(ColorSchemes::from_xshell(xcs), Err(SchemeError::MissingKey { .. })));
+}
+
+#[test]
+fn test_wt_inputs() {
+    let jsonc = std::fs::read_to_string("test/settings.jsonc").unwrap();
+    let jsonc = jsonc.as_str();
+    let schemes = ColorSchemes::from_wt(jsonc).unwrap();
+    assert_eq!(schemes.schemes()[0].name, "Mono");
+    assert_eq!(schemes.schemes()[0].cursor, None);
+    assert_eq!(detect_format(jsonc).unwrap().0, SchemeFormat::WindowsTerminal);
+    assert!(ColorSchemes::from_wt(r#"{"schemes": [], "s": "// not a comment, "}"#).is_ok());
+
+    // An array of schemes, or a single one
+    let settings = String::from_utf8(std::fs::read("test/settings.json").unwrap()).unwrap();
+    let all = ColorSchemes::from_wt(&settings).unwrap();
+    let root: serde_json::Value = serde_json::from_str(&settings).unwrap();
+    let array = root["schemes"].to_string();
+    assert_eq!(ColorSchemes::from_wt(&array).unwrap().schemes().len(), all.schemes().len());
+    assert_eq!(detect_format(&array).unwrap().0, SchemeFormat::WindowsTerminal);
+    let single = root["schemes"][0].to_string();
+    assert_eq!(ColorSchemes::from_wt(&single).unwrap().schemes()[0].name, all.schemes()[0].name);
+
+    let merged = ColorSchemes::from_wt(&single).unwrap().merge_wt(jsonc, None).unwrap();
+    assert_eq!(ColorSchemes::from_wt(&merged).unwrap().schemes().len(), 2);
+}
+
+#[test]
+fn test_wt_merge_jsonc() {
+    let jsonc = std::fs::read_to_string("test/settings.jsonc").unwrap();
+    let settings = String::from_utf8(std::fs::read("test/settings.json").unwrap()).unwrap();
+    let mut mono = ColorSchemes::from_wt(&jsonc).unwrap().schemes()[0].clone();
+    mono.background = Color::rgb(0x10, 0x10, 0x10);
+    let blazer = ColorSchemes::from_wt(&settings).unwrap().schemes()[0].clone();
+    let schemes = ColorSchemes::new(vec![mono, blazer]);
+    let merged = schemes.merge_wt(&jsonc, Some("A")).unwrap();
+
+    // Only the replaced scheme and the additions differ
+    for kept in ["// Comments and trailing commas are allowed\n{\n", "/* Only the colors are mandatory */",
+        "\"name\": \"A\", // thème par défaut\n                \"hidden\": false,"] {
+        assert!(merged.contains(kept), "{}", kept);
+    }
+    assert!(merged.contains("\n            \"name\": \"Blazer\",\n"));
+    let back = ColorSchemes::from_wt(&merged).unwrap();
+    let names: Vec<&str> = back.schemes().iter().map(|schm| schm.name.as_str()).collect();
+    assert_eq!(names, ["Mono", "Blazer"]);
+    assert_eq!(back.schemes()[0].background, Color::rgb(0x10, 0x10, 0x10));
+    let root: serde_json::Value = serde_json::from_str(&profile::jsonc::strip_jsonc(&merged)).unwrap();
+    assert_eq!(root["profiles"]["list"][0]["colorScheme"], "Mono");
+    assert_eq!(root["profiles"]["list"][0]["hidden"], false);
+
+    // Line breaks and indentation follow the settings
+    let crlf = jsonc.replace('\n', "\r\n");
+    assert_eq!(schemes.merge_wt(&crlf, Some("A")).unwrap(), merged.replace('\n', "\r\n"));
+    let tabs = jsonc.replace("    ", "\t");
+    assert_eq!(schemes.merge_wt(&tabs, Some("A")).unwrap(), merged.replace("    ", "\t"));
+
+    // Settings without schemes nor profile color get both
+    let bare = "{\n  // Nothing yet\n  \"profiles\": { \"defaults\": {} }\n}\n";
+    let merged = schemes.merge_wt(bare, Some("defaults")).unwrap();
+    assert!(merged.starts_with("{\n  // Nothing yet\n  \"profiles\": { \"defaults\": {\n    \"colorScheme\": \"Mono\"\n  } },\n  \"schemes\": [\n    {\n      \"name\": \"Mono\","));
+    assert_eq!(ColorSchemes::from_wt(&merged).unwrap().schemes().len(), 2);
+}
+
+#[test]
+fn test_wt_fragment() {
+    let settings = std::fs::read("test/settings.json").unwrap();
+    let fragment = convert(settings.as_slice(), SchemeFormat::WindowsTerminal, SchemeFormat::WindowsTerminalFragment).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&fragment).unwrap();
+    assert!(json.get("$schema").is_none());
+    assert_eq!(json["profiles"], serde_json::json!([]));
+    let wt = convert(settings.as_slice(), SchemeFormat::WindowsTerminal, SchemeFormat::WindowsTerminal).unwrap();
+    assert_eq!(convert(fragment.as_bytes(), SchemeFormat::WindowsTerminalFragment, SchemeFormat::WindowsTerminal).unwrap(), wt);
+
+    let schemes = ColorSchemes::from_wt(&fragment).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&schemes.to_wt_fragment_with_profile(Some("Themed"))).unwrap();
+    assert_eq!(json["profiles"][0]["name"], "Themed");
+    assert_eq!(json["profiles"][0]["colorScheme"], schemes.schemes()[0].name.as_str());
+    assert!(json["profiles"][0]["guid"].as_str().unwrap().starts_with('{'));
+    assert_eq!(SchemeFormat::from_name("wt-fragment").unwrap(), SchemeFormat::WindowsTerminalFragment);
+    // Windows Terminal only loads `*.json` fragments
+    let (file_name, _) = &schemes.to_literal_per_scheme(SchemeFormat::WindowsTerminalFragment)[0];
+    assert_eq!(file_name, &format!("{}.json", schemes.schemes()[0].name));
+}
+
+#[test]
+fn test_iterm2_p3() {
+    // Display P3 components, converted to sRGB
+    let schm = read_fixture("test/Solarized Dark.itermcolors", SchemeFormat::ITerm2);
+    let solarized = [0x073642, 0xdc322f, 0x859900, 0xb58900, 0x268bd2, 0xd33682, 0x2aa198, 0xeee8d5,
+        0x002b36, 0xcb4b16, 0x586e75, 0x657b83, 0x839496, 0x6c71c4, 0x93a1a1, 0xfdf6e3];
+    assert_eq!(schm.ansi(), solarized.map(|c: u32| Color::rgb((c >> 16) as u8, (c >> 8) as u8, c as u8)));
+    assert_eq!(schm.background, Color::rgb(0x00, 0x2b, 0x36));
+    assert_eq!(schm.selection_background, Some(Color::rgb(0x07, 0x36, 0x42)));
+    assert_eq!(schm.bold, Some(Color::rgb(0x93, 0xa1, 0xa1)));
+    assert_eq!(Color::from_display_p3(1.0, 0.0, 0.0), Color::rgb(255, 0, 0));
+    assert_eq!(Color::from_display_p3(0.6, 0.6, 0.6), Color::rgb(153, 153, 153));
+
+    let src = std::fs::read_to_string("test/Solarized Dark.itermcolors").unwrap();
+    let calibrated = src.replace("<string>P3</string>", "<string>Calibrated</string>");
+    assert!(matches!(ColorSchemes::from_iterm2(&calibrated),
+        Err(SchemeError::InvalidValue { path, value, .. }) if path == "Ansi 0 Color.Color Space" && value == "Calibrated"));
+}
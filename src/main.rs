@@ -1,36 +1,9 @@
-mod profile;
-
-use profile::generic::ColorSchemes;
-use profile::generic::SchemeError;
+use tcconv::{decode_with, detect_format, encode, encoding_for_label, ColorSchemes, DecodePolicy, Decoded, EncodingSource,
+             LineEnding, OutputEncoding, SchemeError, SchemeFormat, SchemePattern, FORMATS};
 use std::{fs, io};
-use std::fmt::Debug;
-use chardetng::EncodingDetector;
-use encoding_rs::{Encoding};
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 use std::io::{Read, stderr, Write};
 
-#[derive(Debug)]
-pub enum SchemeFormat {
-    WindowsTerminal,
-    SecureCRT,
-    XShell,
-    Alacritty,
-    MobaXTerm,
-}
-
-impl SchemeFormat {
-    fn from_str(s: &str) -> Result<SchemeFormat, SchemeError> {
-        match s.to_lowercase().trim() {
-            "alacritty" => Ok(SchemeFormat::Alacritty),
-            "xshell" | "xcs" => Ok(SchemeFormat::XShell),
-            "windowsterminal" | "wt" | "windows terminal" => Ok(SchemeFormat::WindowsTerminal),
-            // "mobaxterm" => Ok(SchemeFormat::MobaXTerm),
-            _ => Err(SchemeError::Unsupported)
-        }
-    }
-}
-
-
 fn main() {
     // Option parsing
     let matches = App::new("TCconv")
@@ -40,13 +13,13 @@ fn main() {
             .long("from")
             .value_name("FROM_FORMAT")
             .takes_value(true)
-            .help("From format. Case insensitive (eg. wt)")
+            .help("From format. Case insensitive (eg. wt). Detected from the input when omitted")
         )
         .arg(Arg::new("to")
             .short('t').long("to")
             .value_name("TO_FORMAT")
             .takes_value(true)
-            .help("To format. Case insensitive (eg. alacritty)")
+            .help("To format. Case insensitive (eg. alacritty). Inferred from the output file extension when omitted")
         )
         .arg(Arg::new("INPUT_FILE")
             .help("Source scheme file")
@@ -60,6 +33,87 @@ fn main() {
             .takes_value(true)
             .help("Target scheme file")
         )
+        .arg(Arg::new("merge")
+            .long("merge")
+            .value_name("SETTINGS_FILE")
+            .takes_value(true)
+            .help("Merge schemes into an existing Windows Terminal settings.json instead of writing a new one")
+        )
+        .arg(Arg::new("profile")
+            .long("profile")
+            .value_name("PROFILE")
+            .takes_value(true)
+            .help("Set colorScheme of the profile with this name or guid (or `defaults`) when merging, \
+                   or add a profile with this name to a wt-fragment")
+        )
+        .arg(Arg::new("select")
+            .short('s').long("select")
+            .value_name("PATTERN")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .help("Only convert schemes matching this name, glob (eg. \"Solarized*\") or /regex/. Repeatable")
+        )
+        .arg(Arg::new("exclude")
+            .short('x').long("exclude")
+            .value_name("PATTERN")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .help("Skip schemes matching this name, glob or /regex/. Repeatable")
+        )
+        .arg(Arg::new("rename")
+            .long("rename")
+            .value_name("OLD=NEW")
+            .takes_value(true)
+            .multiple_occurrences(true)
+            .help("Rename a scheme on output. Repeatable")
+        )
+        .arg(Arg::new("pick")
+            .long("pick")
+            .value_name("NAME")
+            .takes_value(true)
+            .help("Only write the scheme with this name, for formats holding one scheme per file")
+        )
+        .arg(Arg::new("split")
+            .long("split")
+            .value_name("DIR")
+            .takes_value(true)
+            .conflicts_with_all(&["OUTPUT_FILE", "merge", "pick", "profile"])
+            .requires("to")
+            .help("Write every scheme to its own file in DIR, named after the scheme")
+        )
+        .arg(Arg::new("from-encoding")
+            .long("from-encoding")
+            .value_name("ENCODING")
+            .takes_value(true)
+            .help("Input encoding label (eg. utf-16le, gbk). Detected when omitted, a byte order mark wins over detection")
+        )
+        .arg(Arg::new("decode-errors")
+            .long("decode-errors")
+            .value_name("POLICY")
+            .takes_value(true)
+            .possible_values(["strict", "replace"])
+            .default_value("strict")
+            .help("Fail on bytes invalid in the input encoding, or replace them and warn")
+        )
+        .arg(Arg::new("verbose")
+            .short('v').long("verbose")
+            .takes_value(false)
+            .help("Report the input encoding")
+        )
+        .arg(Arg::new("to-encoding")
+            .long("to-encoding")
+            .value_name("ENCODING")
+            .takes_value(true)
+            .help("Output encoding: utf-8, utf-8-bom, utf-16le or utf-16be. Defaults to the one of the output format, \
+                   or of the settings file with --merge")
+        )
+        .arg(Arg::new("line-ending")
+            .long("line-ending")
+            .value_name("EOL")
+            .takes_value(true)
+            .help("Output line ending: lf or crlf. Defaults to the one of the output format, \
+                   or of the settings file with --merge")
+        )
         .get_matches();
 
     match matches.occurrences_of("list") {
@@ -69,74 +123,170 @@ fn main() {
             return;
         }
     };
-    if matches.value_of("from").is_none() || matches.value_of("to").is_none() {
-        print_usage();
-        std::process::exit(-1);
+    if let Err(e) = run(&matches) {
+        stderr().write_all(format!("tcconv: {}\n", e).as_bytes()).unwrap();
+        std::process::exit(e.exit_code());
     }
-    let scheme_from = matches.value_of("from").unwrap();
-    let scheme_to = matches.value_of("to").unwrap();
+}
+
+fn run(matches: &ArgMatches) -> Result<(), SchemeError> {
     let file_name = matches.value_of("INPUT_FILE");
     let output_file = matches.value_of("OUTPUT_FILE");
 
-    let scheme_from = SchemeFormat::from_str(scheme_from).unwrap();
-    let scheme_to = SchemeFormat::from_str(scheme_to).unwrap();
+    let scheme_to = match (matches.value_of("to"), output_file) {
+        (Some(to), _) => { SchemeFormat::from_name(to)? }
+        (None, Some(name)) => { SchemeFormat::from_extension(name)? }
+        (None, None) => {
+            return Err(SchemeError::Usage("cannot infer the output format: pass -t or -o with a known extension".to_string()));
+        }
+    };
     let src = match file_name {
         // From file
-        Some(name) => { fs::read(name) }
+        Some(name) => { read_file(name)? }
         // From stdin
-        None => { io::stdin().bytes().collect() }
-    }.unwrap();
+        None => {
+            let mut buf = Vec::new();
+            io::stdin().read_to_end(&mut buf)
+                .map_err(|source| SchemeError::Io { path: "<stdin>".to_string(), source })?;
+            buf
+        }
+    };
+
+    let forced = matches.value_of("from-encoding").map(encoding_for_label).transpose()?;
+    let policy = match matches.value_of("decode-errors") {
+        Some("replace") => { DecodePolicy::Replace }
+        _ => { DecodePolicy::Strict }
+    };
+    let verbose = matches.is_present("verbose");
+    let input = decode_with(src.as_slice(), forced, policy)?;
+    let (scheme_from, detected) = match matches.value_of("from") {
+        Some(from) => { (SchemeFormat::from_name(from)?, None) }
+        None => {
+            let detected = detect_format(input.text.as_str())?;
+            (detected.0, Some(detected))
+        }
+    };
+    report_decoding(file_name.unwrap_or("<stdin>"), &input, detected, verbose);
 
-    let result = convert(src.as_slice(), scheme_from, scheme_to);
+    let encoding = matches.value_of("to-encoding").map(OutputEncoding::from_name).transpose()?;
+    let line_ending = matches.value_of("line-ending").map(LineEnding::from_name).transpose()?;
+
+    let mut schemes = ColorSchemes::from_literal(input.text.as_str(), scheme_from)?;
+    select_schemes(&mut schemes, matches)?;
+
+    // Merged settings are written back like they were read, other files like the terminal writes them
+    let (result, native) = match matches.value_of("merge") {
+        Some(settings) => {
+            if !matches!(scheme_to, SchemeFormat::WindowsTerminal) {
+                return Err(SchemeError::Usage(format!("--merge only works with {} output, not {}",
+                                                      SchemeFormat::WindowsTerminal, scheme_to)));
+            }
+            let settings_src = read_file(settings)?;
+            let decoded = decode_with(settings_src.as_slice(), None, policy)?;
+            report_decoding(settings, &decoded, None, verbose);
+            let merged = schemes.merge_wt(decoded.text.as_str(), matches.value_of("profile"))?;
+            (merged, (decoded.output_encoding(), LineEnding::detect(&decoded.text)))
+        }
+        None => {
+            let info = scheme_to.info();
+            if let Some(dir) = matches.value_of("split") {
+                return write_split(&schemes, scheme_to, dir, encoding.unwrap_or(info.encoding),
+                                   line_ending.unwrap_or(info.line_ending));
+            }
+            let result = match (scheme_to, matches.value_of("profile")) {
+                (SchemeFormat::WindowsTerminalFragment, profile) => { schemes.to_wt_fragment_with_profile(profile) }
+                (_, Some(_)) => {
+                    return Err(SchemeError::Usage(format!("--profile only works with --merge or {} output",
+                                                          SchemeFormat::WindowsTerminalFragment)));
+                }
+                (_, None) => { schemes.to_literal(scheme_to)? }
+            };
+            (result, (info.encoding, info.line_ending))
+        }
+    };
+    let result = encode(&result, encoding.unwrap_or(native.0), line_ending.unwrap_or(native.1));
     match output_file {
         Some(name) => {
-            let mut file = fs::File::create(name).unwrap();
-            file.write_all(result.as_bytes()).unwrap();
+            fs::write(name, &result)
+                .map_err(|source| SchemeError::Io { path: name.to_string(), source })?;
         }
         None => {
-            io::stdout().write_all(result.as_bytes()).unwrap();
+            io::stdout().write_all(&result)
+                .map_err(|source| SchemeError::Io { path: "<stdout>".to_string(), source })?;
         }
     }
+    Ok(())
 }
 
-fn print_usage() {
-    stderr().write_all(b"-h for usage\n").unwrap();
+/// Warn about replaced data, and tell which encoding and detected format were used with `--verbose`
+fn report_decoding(name: &str, decoded: &Decoded, detected: Option<(SchemeFormat, u8)>, verbose: bool) {
+    if verbose {
+        let source = match decoded.source {
+            EncodingSource::Bom => { "byte order mark" }
+            EncodingSource::Forced => { "--from-encoding" }
+            EncodingSource::Detected => { "detected" }
+        };
+        stderr().write_all(format!("tcconv: {}: {} ({})\n", name, decoded.encoding.name(), source).as_bytes()).unwrap();
+        if let Some((fmt, score)) = detected {
+            stderr().write_all(format!("tcconv: {}: detected {} ({}%)\n", name, fmt, score).as_bytes()).unwrap();
+        }
+    }
+    if decoded.replaced {
+        stderr().write_all(format!("tcconv: warning: {}: invalid {} data replaced with U+FFFD\n",
+                                   name, decoded.encoding.name()).as_bytes()).unwrap();
+    }
 }
 
-fn convert(input: &[u8], scheme_from: SchemeFormat, scheme_to: SchemeFormat) -> Box<String> {
-
-    // Guess encoding.
-    let encoding = guess_encoding(input);
-
-    // Decode into str with giving up unrecognized bytes
-    let (input, _, err) = encoding.decode(input);
-    if err {
-        panic!("Unrecognized format");
+/// Apply `--select`, `--exclude` and `--rename`
+fn select_schemes(schemes: &mut ColorSchemes, matches: &ArgMatches) -> Result<(), SchemeError> {
+    let patterns = |arg: &str| -> Result<Vec<SchemePattern>, SchemeError> {
+        matches.values_of(arg).into_iter().flatten().map(SchemePattern::parse).collect()
     };
-    // Convert str to ColorSchemes
-    let gcs = ColorSchemes::from_literal(input.as_ref(), scheme_from);
-    // ColorSchemes to str
-    gcs.unwrap().to_literal(scheme_to)
+    schemes.retain_matching(&patterns("select")?, &patterns("exclude")?);
+    if schemes.schemes().is_empty() {
+        return Err(SchemeError::NoSchemes);
+    }
+    if let Some(name) = matches.value_of("pick") {
+        schemes.pick(name)?;
+    }
+    for rename in matches.values_of("rename").into_iter().flatten() {
+        let (from, to) = rename.split_once('=').ok_or_else(|| SchemeError::InvalidArgument {
+            value: rename.to_string(),
+            message: "expected OLD=NEW".to_string(),
+        })?;
+        schemes.rename(from, to)?;
+    }
+    Ok(())
 }
 
-fn list_available_formats() {
-    io::stdout().write_all(b"wt,windows terminal,windowsterminal
-xcs,xshell
-alacritty
-").unwrap();
+/// Write one file per scheme into `dir`
+fn write_split(schemes: &ColorSchemes, scheme_to: SchemeFormat, dir: &str,
+               encoding: OutputEncoding, line_ending: LineEnding) -> Result<(), SchemeError> {
+    fs::create_dir_all(dir).map_err(|source| SchemeError::Io { path: dir.to_string(), source })?;
+    for (file_name, content) in schemes.to_literal_per_scheme(scheme_to) {
+        let path = std::path::Path::new(dir).join(file_name);
+        fs::write(&path, encode(&content, encoding, line_ending))
+            .map_err(|source| SchemeError::Io { path: path.display().to_string(), source })?;
+    }
+    Ok(())
 }
 
-
-#[test]
-fn test_ground() {
-    let f = std::fs::File.open("test/alacritty.yml").unwrap();
-    let mut reader = io::BufReader::new(f);
-    let mut buf = String::new();
-    reader.read_to_string(&mut buf).unwrap();
+fn read_file(name: &str) -> Result<Vec<u8>, SchemeError> {
+    fs::read(name).map_err(|source| SchemeError::Io { path: name.to_string(), source })
 }
 
-fn guess_encoding(buf: &[u8]) -> &'static Encoding {
-    let mut det = EncodingDetector::new();
-    det.feed(buf.as_ref(), true);
-    det.guess(None, true)
-}
\ No newline at end of file
+fn list_available_formats() {
+    let mut out = io::stdout();
+    for info in FORMATS {
+        let mut files: Vec<String> = info.extensions.iter().map(|ext| format!("*.{}", ext)).collect();
+        files.extend(info.file_names.iter().map(|name| name.to_string()));
+        let caps = info.capabilities;
+        let features: Vec<&str> = [
+            (caps.multiple_schemes, "multiple schemes"),
+            (caps.cursor, "cursor"),
+            (caps.selection, "selection"),
+            (caps.bold, "bold"),
+        ].iter().filter(|(supported, _)| *supported).map(|(_, feature)| *feature).collect();
+        writeln!(out, "{}\t{}\t{}", info.aliases.join(","), files.join(" "), features.join(", ")).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+
+/// An sRGB color with an optional alpha channel.
+///
+/// `{:X}` / `{:x}` print `RRGGBB` / `rrggbb`, the alternate flag (`{:#X}`) adds the `#` prefix.
+/// Alpha is dropped when printing, no output format supports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color { r, g, b, a: 0xff }
+    }
+
+    /// Build a color from float components in [0, 1], as stored by plist based formats
+    pub fn from_f64(r: f64, g: f64, b: f64) -> Color {
+        let channel = |c: f64| { (c.clamp(0.0, 1.0) * 255.0).round() as u8 };
+        Color::rgb(channel(r), channel(g), channel(b))
+    }
+
+    /// Build a color from Display P3 components in [0, 1], clipping what sRGB cannot show
+    pub fn from_display_p3(r: f64, g: f64, b: f64) -> Color {
+        // Both spaces share the sRGB transfer function and the D65 white point
+        let linear = |c: f64| { if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) } };
+        let gamma = |c: f64| { if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 } };
+        let (r, g, b) = (linear(r), linear(g), linear(b));
+        Color::from_f64(
+            gamma(1.2249401 * r - 0.2249404 * g),
+            gamma(-0.0420569 * r + 1.0420571 * g),
+            gamma(-0.0196376 * r - 0.0786361 * g + 1.0982735 * b),
+        )
+    }
+
+    /// Components in [0, 1]
+    pub fn to_f64(self) -> (f64, f64, f64) {
+        (self.r as f64 / 255.0, self.g as f64 / 255.0, self.b as f64 / 255.0)
+    }
+
+    /// `#RRGGBB`, the most common representation
+    pub fn to_hex(self) -> String {
+        format!("{:#X}", self)
+    }
+
+    /// Decimal `R,G,B` triple used by the INI based formats
+    pub fn to_decimal(self) -> String {
+        format!("{},{},{}", self.r, self.g, self.b)
+    }
+
+    /// Parse any of the syntaxes found in terminal configurations:
+    /// - `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `0xrrggbb` and bare `rrggbb`
+    /// - CSS `rgb(r, g, b)` and `rgba(r, g, b, a)`, alpha being a float in [0, 1]
+    /// - X11 `rgb:r/g/b` with 1 to 4 hex digits per channel
+    /// - decimal `r,g,b` triples
+    /// - CSS named colors
+    pub fn parse(s: &str) -> Option<Color> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex);
+        }
+        if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            return parse_hex(hex).filter(|_| hex.len() == 6 || hex.len() == 8);
+        }
+        if let Some(rgb) = s.strip_prefix("rgb:") {
+            return parse_x11(rgb);
+        }
+        if let Some(args) = s.strip_prefix("rgba(").or_else(|| s.strip_prefix("rgb(")) {
+            return parse_css_function(args.strip_suffix(')')?);
+        }
+        if s.contains(',') {
+            return parse_decimal(s);
+        }
+        if s.len() == 6 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return parse_hex(s);
+        }
+        let name = s.to_lowercase();
+        NAMED_COLORS.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, rgb)| Color::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, *rgb as u8))
+    }
+}
+
+impl fmt::UpperHex for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = if f.alternate() { "#" } else { "" };
+        write!(f, "{}{:02X}{:02X}{:02X}", prefix, self.r, self.g, self.b)
+    }
+}
+
+impl fmt::LowerHex for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = if f.alternate() { "#" } else { "" };
+        write!(f, "{}{:02x}{:02x}{:02x}", prefix, self.r, self.g, self.b)
+    }
+}
+
+/// `rgb`, `rgba`, `rrggbb` or `rrggbbaa`
+fn parse_hex(hex: &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits: Vec<u8> = match hex.len() {
+        // Shorthand digits are doubled, `#abc` is `#aabbcc`
+        3 | 4 => { hex.chars().map(|c| c.to_digit(16).unwrap() as u8 * 0x11).collect() }
+        6 | 8 => {
+            (0..hex.len()).step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect()
+        }
+        _ => { return None; }
+    };
+    Some(Color { r: digits[0], g: digits[1], b: digits[2], a: digits.get(3).copied().unwrap_or(0xff) })
+}
+
+/// X11 `r/g/b` (prefix stripped), scaling every channel to 8 bits
+fn parse_x11(s: &str) -> Option<Color> {
+    let channels = s.split('/')
+        .map(|c| {
+            if c.is_empty() || c.len() > 4 {
+                return None;
+            }
+            let val = u32::from_str_radix(c, 16).ok()?;
+            let max = (1u32 << (4 * c.len())) - 1;
+            Some(((val * 255 + max / 2) / max) as u8)
+        })
+        .collect::<Option<Vec<u8>>>()?;
+    match channels.as_slice() {
+        [r, g, b] => { Some(Color::rgb(*r, *g, *b)) }
+        _ => { None }
+    }
+}
+
+/// Arguments of CSS `rgb()` / `rgba()`
+fn parse_css_function(args: &str) -> Option<Color> {
+    let args: Vec<&str> = args.split(',').map(|a| a.trim()).collect();
+    let (rgb, alpha) = match args.as_slice() {
+        [r, g, b] => { ([*r, *g, *b], None) }
+        [r, g, b, a] => { ([*r, *g, *b], Some(*a)) }
+        _ => { return None; }
+    };
+    let mut color = parse_decimal(&rgb.join(","))?;
+    if let Some(alpha) = alpha {
+        let alpha = alpha.parse::<f64>().ok()?;
+        color.a = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+    Some(color)
+}
+
+/// Decimal `r,g,b` triple
+fn parse_decimal(s: &str) -> Option<Color> {
+    let rgb = s.split(',')
+        .map(|c| c.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+    match rgb.as_slice() {
+        [r, g, b] => { Some(Color::rgb(*r, *g, *b)) }
+        _ => { None }
+    }
+}
+
+/// CSS Color Module Level 4 named colors
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff), ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff), ("beige", 0xf5f5dc), ("bisque", 0xffe4c4), ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd), ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00), ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed), ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff), ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9), ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f), ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000), ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1), ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff), ("dimgray", 0x696969), ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff), ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff), ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520), ("gray", 0x808080), ("green", 0x008000), ("greenyellow", 0xadff2f),
+    ("grey", 0x808080), ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c), ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00), ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080), ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1), ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa), ("lightslategray", 0x778899), ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de), ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000), ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3), ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee), ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1), ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead), ("navy", 0x000080), ("oldlace", 0xfdf5e6), ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23), ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee), ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9), ("peru", 0xcd853f), ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd), ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1), ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072), ("sandybrown", 0xf4a460), ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d), ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa), ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4), ("tan", 0xd2b48c), ("teal", 0x008080), ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347), ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00), ("yellowgreen", 0x9acd32),
+];
@@ -0,0 +1,110 @@
+use crate::profile::format::SchemeFormat;
+use crate::profile::generic::{ColorSchemes, SchemeError};
+use crate::profile::jsonc::strip_jsonc;
+
+/// Candidates scoring this close to the best one make the input ambiguous
+const AMBIGUITY_MARGIN: u8 = 10;
+
+/// Guess the format of a decoded scheme file.
+/// Returns the format and its confidence in percent, or `SchemeError::Ambiguous` with the
+/// formats that cannot be told apart. When the input only looks like one format and its reader
+/// rejects it, the error of that reader is returned.
+pub fn detect_format(s: &str) -> Result<(SchemeFormat, u8), SchemeError> {
+    let sniffed = sniff(s);
+    let mut rejections = Vec::new();
+    let mut candidates: Vec<(SchemeFormat, u8)> = Vec::new();
+    // Only keep formats whose reader actually accepts the input
+    for (fmt, score) in sniffed {
+        match ColorSchemes::from_literal(s, fmt) {
+            Ok(_) => { candidates.push((fmt, score)); }
+            Err(e) => { rejections.push(e); }
+        }
+    }
+    candidates.sort_by(|(_, a), (_, b)| b.cmp(a));
+    match candidates.as_slice() {
+        [] if rejections.len() == 1 => { Err(rejections.remove(0)) }
+        [] => { Err(SchemeError::Undetected) }
+        [(fmt, score)] => { Ok((*fmt, *score)) }
+        [(fmt, score), (_, next), ..] => {
+            if score - next < AMBIGUITY_MARGIN {
+                Err(SchemeError::Ambiguous(candidates.iter()
+                    .filter(|(_, s)| score - s < AMBIGUITY_MARGIN)
+                    .map(|(f, _)| *f)
+                    .collect()))
+            } else {
+                Ok((*fmt, *score))
+            }
+        }
+    }
+}
+
+/// Score every format by the markers found in the input
+fn sniff(s: &str) -> Vec<(SchemeFormat, u8)> {
+    let trimmed = s.trim_start();
+    let mut scores = Vec::new();
+
+    if trimmed.starts_with(['{', '[', '/']) {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&strip_jsonc(s)) {
+            let scheme = json.as_array().and_then(|schemes| schemes.first()).unwrap_or(&json);
+            if json.get("schemes").map(|v| v.is_array()).unwrap_or(false) {
+                scores.push((SchemeFormat::WindowsTerminal, 100));
+            } else if scheme.get("brightBlack").is_some() {
+                scores.push((SchemeFormat::WindowsTerminal, 80));
+            }
+            // JSON is also YAML, nothing else to look for
+            return scores;
+        }
+    }
+    if trimmed.starts_with("<?xml") || trimmed.starts_with("<plist") || trimmed.starts_with("<!DOCTYPE plist") {
+        if s.contains("<key>Ansi 0 Color</key>") {
+            scores.push((SchemeFormat::ITerm2, 100));
+        } else {
+            scores.push((SchemeFormat::ITerm2, 40));
+        }
+        return scores;
+    }
+
+    if let Ok(toml) = toml::from_str::<toml::Value>(s) {
+        let colors = toml.get("colors");
+        if colors.and_then(|c| c.get("ansi")).is_some() {
+            scores.push((SchemeFormat::WezTerm, 95));
+        }
+        if colors.and_then(|c| c.get("primary")).is_some() {
+            scores.push((SchemeFormat::AlacrittyToml, 95));
+        }
+    }
+    if let Ok(yaml) = serde_yaml::from_str::<serde_yaml::Value>(s) {
+        if yaml.get("colors").and_then(|c| c.get("primary")).is_some() {
+            scores.push((SchemeFormat::Alacritty, 95));
+        }
+    }
+
+    let lines: Vec<&str> = s.lines().map(|l| l.trim()).collect();
+    let has_line = |pred: &dyn Fn(&str) -> bool| { lines.iter().any(|l| pred(l)) };
+
+    if has_line(&|l| l.starts_with("B:\"ANSI Color RGB\"=")) {
+        scores.push((SchemeFormat::SecureCRT, 95));
+    } else if has_line(&|l| l.starts_with("B:\"") && l.ends_with("\"=00000008")) {
+        scores.push((SchemeFormat::SecureCRT, 70));
+    }
+    if has_line(&|l| l.contains("(bold)=")) {
+        let names = has_line(&|l| l.eq_ignore_ascii_case("[Names]"));
+        scores.push((SchemeFormat::XShell, if names { 95 } else { 70 }));
+    }
+    if has_line(&|l| l.eq_ignore_ascii_case("[Colors]")) && has_line(&|l| l.starts_with("BoldBlack=")) {
+        scores.push((SchemeFormat::MobaXTerm, 90));
+    }
+    if has_line(&|l| l == "[Color0Intense]") {
+        scores.push((SchemeFormat::Konsole, 95));
+    }
+    if has_line(&|l| l.starts_with("palette=[")) {
+        scores.push((SchemeFormat::GnomeTerminal, 90));
+    }
+    if has_line(&|l| l.starts_with("color0 ") || l.starts_with("color0\t")) {
+        scores.push((SchemeFormat::Kitty, 80));
+    }
+    if has_line(&|l| l.split(':').next().map(|k| k.ends_with(".color0") || k.ends_with("*color0")).unwrap_or(false)) {
+        scores.push((SchemeFormat::Xresources, 85));
+    }
+    scores
+}
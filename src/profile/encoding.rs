@@ -0,0 +1,175 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{DecoderResult, Encoding};
+use crate::profile::error::SchemeError;
+
+/// What to do with bytes that are invalid in the input encoding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodePolicy {
+    /// Fail with the offset of the first invalid byte
+    Strict,
+    /// Replace invalid data with U+FFFD
+    Replace,
+}
+
+/// How the input encoding was chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodingSource {
+    Bom,
+    Forced,
+    Detected,
+}
+
+#[derive(Debug)]
+pub struct Decoded {
+    pub text: String,
+    pub encoding: &'static Encoding,
+    pub source: EncodingSource,
+    /// Invalid data was replaced, only with `DecodePolicy::Replace`
+    pub replaced: bool,
+}
+
+impl Decoded {
+    /// Encoding writing the text back as it was read: UTF-16 or UTF-8 with the byte order mark
+    /// it had, plain UTF-8 otherwise
+    pub fn output_encoding(&self) -> OutputEncoding {
+        match self.encoding {
+            e if e == encoding_rs::UTF_16LE => { OutputEncoding::Utf16LeBom }
+            e if e == encoding_rs::UTF_16BE => { OutputEncoding::Utf16BeBom }
+            e if e == encoding_rs::UTF_8 && self.source == EncodingSource::Bom => { OutputEncoding::Utf8Bom }
+            _ => { OutputEncoding::Utf8 }
+        }
+    }
+}
+
+/// Encoding of written files. UTF-16 is always written with a BOM so readers can tell the byte order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16LeBom,
+    Utf16BeBom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl OutputEncoding {
+    pub fn from_name(s: &str) -> Result<OutputEncoding, SchemeError> {
+        match s.trim().to_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(OutputEncoding::Utf8),
+            "utf-8-bom" | "utf8-bom" | "utf-8 bom" => Ok(OutputEncoding::Utf8Bom),
+            "utf-16le" | "utf16le" | "utf-16" | "utf16" => Ok(OutputEncoding::Utf16LeBom),
+            "utf-16be" | "utf16be" => Ok(OutputEncoding::Utf16BeBom),
+            _ => Err(SchemeError::InvalidArgument {
+                value: s.to_string(),
+                message: "expected utf-8, utf-8-bom, utf-16le or utf-16be".to_string(),
+            })
+        }
+    }
+
+    pub fn encode(self, s: &str) -> Vec<u8> {
+        match self {
+            OutputEncoding::Utf8 => { s.as_bytes().to_vec() }
+            OutputEncoding::Utf8Bom => { [&[0xef, 0xbb, 0xbf], s.as_bytes()].concat() }
+            OutputEncoding::Utf16LeBom => {
+                std::iter::once(0xfeff).chain(s.encode_utf16()).flat_map(|u| u.to_le_bytes()).collect()
+            }
+            OutputEncoding::Utf16BeBom => {
+                std::iter::once(0xfeff).chain(s.encode_utf16()).flat_map(|u| u.to_be_bytes()).collect()
+            }
+        }
+    }
+}
+
+impl LineEnding {
+    pub fn from_name(s: &str) -> Result<LineEnding, SchemeError> {
+        match s.trim().to_lowercase().as_str() {
+            "lf" | "unix" => Ok(LineEnding::Lf),
+            "crlf" | "windows" => Ok(LineEnding::CrLf),
+            _ => Err(SchemeError::InvalidArgument { value: s.to_string(), message: "expected lf or crlf".to_string() })
+        }
+    }
+
+    /// Line ending of `s`, LF unless it has CRLF line breaks
+    pub fn detect(s: &str) -> LineEnding {
+        if s.contains("\r\n") { LineEnding::CrLf } else { LineEnding::Lf }
+    }
+
+    /// Rewrite every line break of `s`, whatever it was
+    pub fn apply(self, s: &str) -> String {
+        let lf = s.replace("\r\n", "\n");
+        match self {
+            LineEnding::Lf => { lf }
+            LineEnding::CrLf => { lf.replace('\n', "\r\n") }
+        }
+    }
+}
+
+/// Serialize writer output into the bytes of a file
+pub fn encode(s: &str, encoding: OutputEncoding, line_ending: LineEnding) -> Vec<u8> {
+    encoding.encode(&line_ending.apply(s))
+}
+
+/// Look up an input encoding by its WHATWG label (utf-8, utf-16le, gbk, windows-1252...)
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding, SchemeError> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| SchemeError::InvalidArgument { value: label.to_string(), message: "unknown encoding".to_string() })
+}
+
+/// Guess the encoding of a scheme file without a byte order mark
+pub fn guess_encoding(buf: &[u8]) -> &'static Encoding {
+    let mut det = EncodingDetector::new();
+    det.feed(buf, true);
+    det.guess(None, true)
+}
+
+/// Decode a scheme file, guessing its encoding and failing on invalid data
+pub fn decode(input: &[u8]) -> Result<String, SchemeError> {
+    decode_with(input, None, DecodePolicy::Strict).map(|decoded| decoded.text)
+}
+
+/// Decode a scheme file. A byte order mark wins over detection, `encoding` wins over both.
+pub fn decode_with(input: &[u8], encoding: Option<&'static Encoding>, policy: DecodePolicy) -> Result<Decoded, SchemeError> {
+    let (encoding, bom_len, source) = match (Encoding::for_bom(input), encoding) {
+        // A byte order mark matching the forced encoding is still dropped
+        (Some((bom, len)), Some(forced)) if bom == forced => { (forced, len, EncodingSource::Forced) }
+        (_, Some(forced)) => { (forced, 0, EncodingSource::Forced) }
+        (Some((bom, len)), None) => { (bom, len, EncodingSource::Bom) }
+        (None, None) => { (guess_encoding(input), 0, EncodingSource::Detected) }
+    };
+    let body = &input[bom_len..];
+    let (text, replaced) = match policy {
+        DecodePolicy::Replace => {
+            let (text, replaced) = encoding.decode_without_bom_handling(body);
+            (text.into_owned(), replaced)
+        }
+        DecodePolicy::Strict => {
+            let text = decode_strict(encoding, body).map_err(|offset| SchemeError::Decode {
+                encoding: encoding.name().to_string(),
+                offset: bom_len + offset,
+            })?;
+            (text, false)
+        }
+    };
+    Ok(Decoded { text, encoding, source, replaced })
+}
+
+/// Decode without replacement, returning the offset of the first invalid byte on failure
+fn decode_strict(encoding: &'static Encoding, input: &[u8]) -> Result<String, usize> {
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let capacity = decoder.max_utf8_buffer_length_without_replacement(input.len()).unwrap_or(input.len() * 3);
+    let mut text = String::with_capacity(capacity);
+    let mut consumed = 0;
+    loop {
+        let (result, read) = decoder.decode_to_string_without_replacement(&input[consumed..], &mut text, true);
+        consumed += read;
+        match result {
+            DecoderResult::InputEmpty => { return Ok(text); }
+            DecoderResult::OutputFull => { text.reserve(input.len() - consumed + 16); }
+            DecoderResult::Malformed(bad, after) => { return Err(consumed - bad as usize - after as usize); }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+use crate::profile::format::SchemeFormat;
+
+#[derive(Debug)]
+pub enum SchemeError {
+    /// Format name or file extension not recognized
+    UnknownFormat(String),
+    /// Input matches several formats equally well
+    Ambiguous(Vec<SchemeFormat>),
+    /// No reader accepts the input
+    Undetected,
+    /// Input could not be decoded as text
+    Decode { encoding: String, offset: usize },
+    /// Input is not valid in the syntax of the format (JSON, YAML, INI...)
+    Syntax { format: SchemeFormat, message: String, location: Option<Location> },
+    /// A key required by the format is absent
+    MissingKey { format: SchemeFormat, scheme: Option<String>, path: String },
+    /// A value could not be understood, typically a malformed color
+    InvalidValue { format: SchemeFormat, scheme: Option<String>, path: String, value: String },
+    /// Malformed command line value, such as a scheme pattern
+    InvalidArgument { value: String, message: String },
+    /// Missing or conflicting command line options
+    Usage(String),
+    /// Nothing to write, or no scheme to assign to a profile
+    NoSchemes,
+    /// Windows Terminal profile not found while merging
+    NoSuchProfile(String),
+    /// Scheme to rename or pick not found
+    NoSuchScheme(String),
+    /// Several schemes given to a format holding one scheme per file
+    MultipleSchemes { format: SchemeFormat, count: usize },
+    Io { path: String, source: std::io::Error },
+}
+
+/// 1-based position in the input
+#[derive(Debug, Clone, Copy)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SchemeError {
+    /// Process exit code, distinct for every class of failure
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SchemeError::UnknownFormat(_) | SchemeError::Ambiguous(_) | SchemeError::Undetected
+            | SchemeError::InvalidArgument { .. } | SchemeError::Usage(_) => { 2 }
+            SchemeError::Decode { .. } => { 3 }
+            SchemeError::Syntax { .. } => { 4 }
+            SchemeError::MissingKey { .. } | SchemeError::InvalidValue { .. } => { 5 }
+            SchemeError::NoSchemes | SchemeError::NoSuchProfile(_) | SchemeError::NoSuchScheme(_)
+            | SchemeError::MultipleSchemes { .. } => { 6 }
+            SchemeError::Io { .. } => { 7 }
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+impl fmt::Display for SchemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let in_scheme = |scheme: &Option<String>| {
+            scheme.as_ref().map(|name| format!(" in scheme \"{}\"", name)).unwrap_or_default()
+        };
+        match self {
+            SchemeError::UnknownFormat(name) => { write!(f, "unknown format \"{}\" (-l lists supported formats)", name) }
+            SchemeError::Ambiguous(candidates) => {
+                let candidates: Vec<String> = candidates.iter().map(|fmt| fmt.to_string()).collect();
+                write!(f, "ambiguous format, could be one of: {} (use -f or -t to choose)", candidates.join(", "))
+            }
+            SchemeError::Undetected => { write!(f, "unable to detect the input format (use -f to specify it)") }
+            SchemeError::Decode { encoding, offset } => {
+                write!(f, "input is not valid {} text (invalid data at byte {})", encoding, offset)
+            }
+            SchemeError::Syntax { format, message, location } => {
+                match location {
+                    Some(location) => { write!(f, "{} syntax error at {}: {}", format, location, message) }
+                    None => { write!(f, "{} syntax error: {}", format, message) }
+                }
+            }
+            SchemeError::MissingKey { format, scheme, path } => {
+                write!(f, "{}: missing `{}`{}", format, path, in_scheme(scheme))
+            }
+            SchemeError::InvalidValue { format, scheme, path, value } => {
+                write!(f, "{}: invalid value \"{}\" for `{}`{}", format, value, path, in_scheme(scheme))
+            }
+            SchemeError::InvalidArgument { value, message } => { write!(f, "invalid argument \"{}\": {}", value, message) }
+            SchemeError::Usage(message) => { write!(f, "{} (-h for usage)", message) }
+            SchemeError::NoSchemes => { write!(f, "no color scheme found") }
+            SchemeError::NoSuchProfile(profile) => { write!(f, "no profile named \"{}\" in settings", profile) }
+            SchemeError::NoSuchScheme(name) => { write!(f, "no scheme named \"{}\"", name) }
+            SchemeError::MultipleSchemes { format, count } => {
+                write!(f, "{} holds a single scheme per file but {} were found (use --select, --pick or --split)", format, count)
+            }
+            SchemeError::Io { path, source } => { write!(f, "{}: {}", path, source) }
+        }
+    }
+}
+
+impl std::error::Error for SchemeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchemeError::Io { source, .. } => { Some(source) }
+            _ => { None }
+        }
+    }
+}
+
+/// Format and scheme a reader is working on, attached to the errors it raises
+#[derive(Clone)]
+pub(crate) struct ErrorContext {
+    format: SchemeFormat,
+    scheme: Option<String>,
+}
+
+impl ErrorContext {
+    pub fn new(format: SchemeFormat) -> ErrorContext {
+        ErrorContext { format, scheme: None }
+    }
+
+    pub fn scheme(&self, name: &str) -> ErrorContext {
+        ErrorContext { format: self.format, scheme: Some(name.to_string()) }
+    }
+
+    /// Parsers often end their message with the position, which `location` already gives
+    pub fn syntax(&self, message: impl ToString, location: Option<Location>) -> SchemeError {
+        let mut message = message.to_string();
+        if let Some(location) = location {
+            let suffix = format!(" at line {} column {}", location.line, location.column);
+            if message.ends_with(&suffix) {
+                message.truncate(message.len() - suffix.len());
+            }
+        }
+        SchemeError::Syntax { format: self.format, message, location }
+    }
+
+    pub fn missing(&self, path: &str) -> SchemeError {
+        SchemeError::MissingKey { format: self.format, scheme: self.scheme.clone(), path: path.to_string() }
+    }
+
+    pub fn invalid(&self, path: &str, value: &str) -> SchemeError {
+        SchemeError::InvalidValue {
+            format: self.format,
+            scheme: self.scheme.clone(),
+            path: path.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// Parse the color found at `path`, failing with `MissingKey` or `InvalidValue`
+    pub fn color<T>(&self, path: &str, value: Option<&str>, parse: impl Fn(&str) -> Option<T>) -> Result<T, SchemeError> {
+        let value = value.ok_or_else(|| self.missing(path))?;
+        parse(value).ok_or_else(|| self.invalid(path, value))
+    }
+
+    /// Like `color`, but an absent key yields `None` instead of `MissingKey`
+    pub fn optional_color<T>(&self, path: &str, value: Option<&str>, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>, SchemeError> {
+        value.map(|value| parse(value).ok_or_else(|| self.invalid(path, value))).transpose()
+    }
+}
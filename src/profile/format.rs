@@ -0,0 +1,303 @@
+use std::fmt;
+use crate::profile::encoding::{LineEnding, OutputEncoding};
+use crate::profile::generic::{ColorSchemes, SchemeError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SchemeFormat {
+    WindowsTerminal,
+    WindowsTerminalFragment,
+    SecureCRT,
+    SecureCRTSchemes,
+    XShell,
+    Alacritty,
+    AlacrittyToml,
+    MobaXTerm,
+    ITerm2,
+    Kitty,
+    WezTerm,
+    Konsole,
+    Xresources,
+    GnomeTerminal,
+}
+
+/// Parses a decoded file into schemes
+pub trait SchemeReader: Sync {
+    fn read(&self, s: &str) -> Result<Box<ColorSchemes>, SchemeError>;
+}
+
+/// Serializes schemes into the content of a file
+pub trait SchemeWriter: Sync {
+    fn write(&self, schemes: &ColorSchemes) -> String;
+}
+
+impl<F> SchemeReader for F where F: Fn(&str) -> Result<Box<ColorSchemes>, SchemeError> + Sync {
+    fn read(&self, s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        self(s)
+    }
+}
+
+impl<F> SchemeWriter for F where F: Fn(&ColorSchemes) -> String + Sync {
+    fn write(&self, schemes: &ColorSchemes) -> String {
+        self(schemes)
+    }
+}
+
+/// What a format is able to hold besides the 16 ANSI colors, background and foreground
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    /// Several schemes fit in one file
+    pub multiple_schemes: bool,
+    pub cursor: bool,
+    pub selection: bool,
+    pub bold: bool,
+}
+
+pub struct FormatInfo {
+    pub format: SchemeFormat,
+    /// Names accepted by `-f` / `-t`, the first one is the canonical name
+    pub aliases: &'static [&'static str],
+    /// Lower case file extensions, without the dot
+    pub extensions: &'static [&'static str],
+    /// File names the terminal gives its own files, recognized regardless of their extension.
+    /// Matched ignoring case and the leading dot of dotfiles.
+    pub file_names: &'static [&'static str],
+    /// Extension of the files `--split` writes, the one the terminal loads
+    pub output_extension: &'static str,
+    pub capabilities: Capabilities,
+    /// Encoding and line ending the terminal itself writes
+    pub encoding: OutputEncoding,
+    pub line_ending: LineEnding,
+    pub reader: &'static dyn SchemeReader,
+    pub writer: &'static dyn SchemeWriter,
+}
+
+impl FormatInfo {
+    pub fn name(&self) -> &'static str {
+        self.aliases[0]
+    }
+}
+
+const fn capabilities(multiple_schemes: bool, cursor: bool, selection: bool, bold: bool) -> Capabilities {
+    Capabilities { multiple_schemes, cursor, selection, bold }
+}
+
+/// Every supported format. The order is the one of `-l`.
+pub static FORMATS: &[FormatInfo] = &[
+    FormatInfo {
+        format: SchemeFormat::WindowsTerminal,
+        aliases: &["wt", "windows terminal", "windowsterminal"],
+        extensions: &["json"],
+        file_names: &[],
+        output_extension: "json",
+        capabilities: capabilities(true, true, true, false),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_wt,
+        writer: &ColorSchemes::to_wt,
+    },
+    FormatInfo {
+        format: SchemeFormat::WindowsTerminalFragment,
+        aliases: &["wt-fragment", "fragment", "windows terminal fragment"],
+        // `.json` is taken by settings.json
+        extensions: &[],
+        file_names: &[],
+        output_extension: "json",
+        capabilities: capabilities(true, true, true, false),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_wt,
+        writer: &ColorSchemes::to_wt_fragment,
+    },
+    FormatInfo {
+        format: SchemeFormat::XShell,
+        aliases: &["xcs", "xshell"],
+        extensions: &["xcs"],
+        file_names: &[],
+        output_extension: "xcs",
+        capabilities: capabilities(true, false, false, true),
+        encoding: OutputEncoding::Utf16LeBom,
+        line_ending: LineEnding::CrLf,
+        reader: &ColorSchemes::from_xshell,
+        writer: &ColorSchemes::to_xshell,
+    },
+    FormatInfo {
+        format: SchemeFormat::Alacritty,
+        aliases: &["alacritty", "alacritty-yml", "alacritty-yaml"],
+        extensions: &["yml", "yaml"],
+        file_names: &[],
+        output_extension: "yml",
+        capabilities: capabilities(false, true, true, true),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_alacritty,
+        writer: &ColorSchemes::to_alacritty,
+    },
+    FormatInfo {
+        format: SchemeFormat::AlacrittyToml,
+        aliases: &["alacritty-toml"],
+        extensions: &["toml"],
+        file_names: &["alacritty.toml"],
+        output_extension: "toml",
+        capabilities: capabilities(false, true, true, true),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_alacritty_toml,
+        writer: &ColorSchemes::to_alacritty_toml,
+    },
+    FormatInfo {
+        format: SchemeFormat::SecureCRT,
+        aliases: &["crt", "securecrt"],
+        extensions: &["ini"],
+        file_names: &[],
+        output_extension: "ini",
+        // A session holds a single `ANSI Color RGB` palette
+        capabilities: capabilities(false, false, false, false),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_securecrt,
+        writer: &ColorSchemes::to_securecrt,
+    },
+    FormatInfo {
+        format: SchemeFormat::SecureCRTSchemes,
+        aliases: &["crt-schemes", "securecrt-schemes", "securecrt color schemes"],
+        extensions: &[],
+        file_names: &["Color Schemes.ini"],
+        output_extension: "ini",
+        // Foreground and background only, the ANSI colors live in the sessions
+        capabilities: capabilities(true, false, false, false),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_securecrt,
+        writer: &ColorSchemes::to_securecrt_schemes,
+    },
+    FormatInfo {
+        format: SchemeFormat::MobaXTerm,
+        aliases: &["moba", "mobaxterm"],
+        extensions: &["ini"],
+        file_names: &["MobaXterm.ini"],
+        output_extension: "ini",
+        capabilities: capabilities(false, true, false, true),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::CrLf,
+        reader: &ColorSchemes::from_mobaxterm,
+        writer: &ColorSchemes::to_mobaxterm,
+    },
+    FormatInfo {
+        format: SchemeFormat::ITerm2,
+        aliases: &["iterm", "iterm2", "itermcolors"],
+        extensions: &["itermcolors"],
+        file_names: &[],
+        output_extension: "itermcolors",
+        capabilities: capabilities(false, true, true, true),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_iterm2,
+        writer: &ColorSchemes::to_iterm2,
+    },
+    FormatInfo {
+        format: SchemeFormat::Kitty,
+        aliases: &["kitty"],
+        extensions: &["conf"],
+        file_names: &[],
+        output_extension: "conf",
+        capabilities: capabilities(false, true, true, false),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_kitty,
+        writer: &ColorSchemes::to_kitty,
+    },
+    FormatInfo {
+        format: SchemeFormat::WezTerm,
+        aliases: &["wezterm"],
+        extensions: &["toml"],
+        file_names: &[],
+        output_extension: "toml",
+        capabilities: capabilities(false, true, true, false),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_wezterm,
+        writer: &ColorSchemes::to_wezterm,
+    },
+    FormatInfo {
+        format: SchemeFormat::Konsole,
+        aliases: &["konsole", "colorscheme"],
+        extensions: &["colorscheme"],
+        file_names: &[],
+        output_extension: "colorscheme",
+        capabilities: capabilities(false, false, false, true),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_konsole,
+        writer: &ColorSchemes::to_konsole,
+    },
+    FormatInfo {
+        format: SchemeFormat::Xresources,
+        aliases: &["xresources", "xdefaults"],
+        extensions: &["xresources"],
+        file_names: &[".Xresources", ".Xdefaults"],
+        output_extension: "xresources",
+        capabilities: capabilities(false, true, false, true),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_xresources,
+        writer: &ColorSchemes::to_xresources,
+    },
+    FormatInfo {
+        format: SchemeFormat::GnomeTerminal,
+        aliases: &["dconf", "gnome-terminal", "gnome terminal", "gnometerminal"],
+        extensions: &["dconf"],
+        file_names: &[],
+        output_extension: "dconf",
+        capabilities: capabilities(true, true, true, true),
+        encoding: OutputEncoding::Utf8,
+        line_ending: LineEnding::Lf,
+        reader: &ColorSchemes::from_gnome_terminal,
+        writer: &ColorSchemes::to_gnome_terminal,
+    },
+];
+
+/// Canonical name, the one `-f` / `-t` and `-l` use
+impl fmt::Display for SchemeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.info().name())
+    }
+}
+
+impl SchemeFormat {
+    pub fn info(self) -> &'static FormatInfo {
+        FORMATS.iter()
+            .find(|info| info.format == self)
+            .expect("every format is registered")
+    }
+
+    /// Look up a format by one of its aliases, case insensitive
+    pub fn from_name(s: &str) -> Result<SchemeFormat, SchemeError> {
+        let name = s.trim().to_lowercase();
+        FORMATS.iter()
+            .find(|info| info.aliases.contains(&name.as_str()))
+            .map(|info| info.format)
+            .ok_or_else(|| SchemeError::UnknownFormat(s.to_string()))
+    }
+
+    pub fn from_extension(path: &str) -> Result<SchemeFormat, SchemeError> {
+        let path = std::path::Path::new(path);
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_lowercase();
+        // Some files are recognized by name rather than extension
+        let by_name = FORMATS.iter().find(|info| {
+            info.file_names.iter().any(|name| name.trim_start_matches('.').eq_ignore_ascii_case(file_name.trim_start_matches('.')))
+        });
+        if let Some(info) = by_name {
+            return Ok(info.format);
+        }
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        let candidates: Vec<SchemeFormat> = FORMATS.iter()
+            .filter(|info| info.extensions.contains(&ext.as_str()))
+            .map(|info| info.format)
+            .collect();
+        match candidates.as_slice() {
+            [] => { Err(SchemeError::UnknownFormat(file_name)) }
+            [fmt] => { Ok(*fmt) }
+            _ => { Err(SchemeError::Ambiguous(candidates)) }
+        }
+    }
+}
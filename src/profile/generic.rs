@@ -1,43 +1,49 @@
 use std::collections::{BTreeMap, HashMap};
 use ini::Properties;
-use serde_json::{Error, Value};
-use crate::SchemeFormat;
-
-type RGBColor = u32;
+use serde_json::Value;
+use crate::profile::format::SchemeFormat;
+use crate::profile::select::SchemePattern;
+use crate::profile::color::Color;
+use crate::profile::error::{ErrorContext, Location};
+use crate::profile::jsonc::{self, parse_jsonc, Edits, Node, NodeKind};
+pub use crate::profile::error::SchemeError;
 
 #[derive(Debug)]
 pub struct ColorSchemes(Vec<ColorScheme>);
 
 
-#[derive(Debug)]
-pub enum SchemeError {
-    Unsupported,
-    Invalid,
-}
-
 #[derive(Debug, Clone)]
 pub struct ColorScheme {
-    name: String,
-    black: RGBColor,
-    red: RGBColor,
-    green: RGBColor,
-    yellow: RGBColor,
-    blue: RGBColor,
-    magenta: RGBColor,
-    cyan: RGBColor,
-    white: RGBColor,
-
-    bright_black: RGBColor,
-    bright_red: RGBColor,
-    bright_green: RGBColor,
-    bright_yellow: RGBColor,
-    bright_blue: RGBColor,
-    bright_magenta: RGBColor,
-    bright_cyan: RGBColor,
-    bright_white: RGBColor,
-
-    background: RGBColor,
-    foreground: RGBColor,
+    pub name: String,
+    /// Credited in the metadata of WezTerm and kitty themes
+    pub author: Option<String>,
+    pub black: Color,
+    pub red: Color,
+    pub green: Color,
+    pub yellow: Color,
+    pub blue: Color,
+    pub magenta: Color,
+    pub cyan: Color,
+    pub white: Color,
+
+    pub bright_black: Color,
+    pub bright_red: Color,
+    pub bright_green: Color,
+    pub bright_yellow: Color,
+    pub bright_blue: Color,
+    pub bright_magenta: Color,
+    pub bright_cyan: Color,
+    pub bright_white: Color,
+
+    pub background: Color,
+    pub foreground: Color,
+
+    // Formats without these colors derive them from `foreground` and `background`
+    pub cursor: Option<Color>,
+    pub cursor_text: Option<Color>,
+    pub selection_background: Option<Color>,
+    pub selection_foreground: Option<Color>,
+    pub bold: Option<Color>,
 }
 
 impl FromIterator<ColorScheme> for ColorSchemes {
@@ -61,256 +67,322 @@ impl ColorSchemes {
         ColorSchemes(v)
     }
 
-    pub fn to_wt(&self) -> Box<String> {
+    pub fn schemes(&self) -> &[ColorScheme] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<ColorScheme> {
+        self.0
+    }
+
+    /// Keep the schemes matching any of `include` (all of them when empty) and none of `exclude`
+    pub fn retain_matching(&mut self, include: &[SchemePattern], exclude: &[SchemePattern]) {
+        self.0.retain(|schm| {
+            (include.is_empty() || include.iter().any(|p| p.matches(&schm.name)))
+                && !exclude.iter().any(|p| p.matches(&schm.name))
+        });
+    }
+
+    /// Keep only the scheme named `name`
+    pub fn pick(&mut self, name: &str) -> Result<(), SchemeError> {
+        let schm = self.0.iter()
+            .find(|schm| schm.name == name)
+            .ok_or_else(|| SchemeError::NoSuchScheme(name.to_string()))?;
+        self.0 = vec![schm.clone()];
+        Ok(())
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), SchemeError> {
+        let schm = self.0.iter_mut()
+            .find(|schm| schm.name == from)
+            .ok_or_else(|| SchemeError::NoSuchScheme(from.to_string()))?;
+        schm.name = to.to_string();
+        Ok(())
+    }
+
+    pub fn to_wt(&self) -> String {
         let mut jsonobj = BTreeMap::<String, Value>::new();
         jsonobj.insert("$schema".to_string(), Value::String("https://aka.ms/terminal-profiles-schema".to_string()));
-        let schemes: Vec<Value> = self.0.clone().into_iter().map(|schm| {
-            let mut bt = serde_json::Map::<String, Value>::new();
-            bt.insert("name".to_string(), Value::String(schm.name));
-            bt.insert("background".to_string(), Value::String(schm.background.to_hex_repr()));
-            bt.insert("black".to_string(), Value::String(schm.black.to_hex_repr()));
-            bt.insert("blue".to_string(), Value::String(schm.blue.to_hex_repr()));
-            bt.insert("brightBlack".to_string(), Value::String(schm.bright_black.to_hex_repr()));
-            bt.insert("brightBlue".to_string(), Value::String(schm.bright_blue.to_hex_repr()));
-            bt.insert("brightCyan".to_string(), Value::String(schm.bright_cyan.to_hex_repr()));
-            bt.insert("brightGreen".to_string(), Value::String(schm.bright_green.to_hex_repr()));
-            bt.insert("brightPurple".to_string(), Value::String(schm.bright_magenta.to_hex_repr()));
-            bt.insert("brightRed".to_string(), Value::String(schm.bright_red.to_hex_repr()));
-            bt.insert("brightWhite".to_string(), Value::String(schm.bright_white.to_hex_repr()));
-            bt.insert("brightYellow".to_string(), Value::String(schm.bright_yellow.to_hex_repr()));
-            bt.insert("cursorColor".to_string(), Value::String(schm.foreground.to_hex_repr()));
-            bt.insert("cyan".to_string(), Value::String(schm.cyan.to_hex_repr()));
-            bt.insert("foreground".to_string(), Value::String(schm.foreground.to_hex_repr()));
-            bt.insert("green".to_string(), Value::String(schm.green.to_hex_repr()));
-            bt.insert("purple".to_string(), Value::String(schm.magenta.to_hex_repr()));
-            bt.insert("red".to_string(), Value::String(schm.red.to_hex_repr()));
-            bt.insert("selectionBackground".to_string(), Value::String(schm.foreground.to_hex_repr()));
-            bt.insert("white".to_string(), Value::String(schm.white.to_hex_repr()));
-            bt.insert("yellow".to_string(), Value::String(schm.yellow.to_hex_repr()));
-            Value::Object(bt)
-        }).collect();
+        let schemes: Vec<Value> = self.0.iter().map(|schm| { schm.to_wt_value() }).collect();
         jsonobj.insert("schemes".to_string(), Value::Array(schemes));
-        Box::new(serde_json::to_string_pretty(&jsonobj).unwrap())
+        serde_json::to_string_pretty(&jsonobj).unwrap()
     }
 
-    pub fn from_wt(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
-        let kv: Result<HashMap<String, serde_json::Value>, Error> = serde_json::from_str(s);
-        let kv = match kv {
-            Ok(map) => { map }
-            Err(_) => { return Err(SchemeError::Invalid); }
-        };
-        // If it is a complete settings.json
-        if !kv.contains_key("schemes") {
-            return Err(SchemeError::Invalid);
+    /// Windows Terminal fragment extension, to drop in `%LOCALAPPDATA%\\Microsoft\\Windows Terminal\\Fragments\\<app>`
+    pub fn to_wt_fragment(&self) -> String {
+        self.to_wt_fragment_with_profile(None)
+    }
+
+    /// Fragment that also adds a profile named `profile` using the first scheme
+    pub fn to_wt_fragment_with_profile(&self, profile: Option<&str>) -> String {
+        let mut jsonobj = BTreeMap::<String, Value>::new();
+        let profiles: Vec<Value> = profile.zip(self.0.first()).map(|(profile, schm)| {
+            serde_json::json!({
+                "name": profile,
+                "guid": format!("{{{}}}", profile_uuid(profile, 0)),
+                "colorScheme": schm.name,
+            })
+        }).into_iter().collect();
+        jsonobj.insert("profiles".to_string(), Value::Array(profiles));
+        let schemes: Vec<Value> = self.0.iter().map(|schm| { schm.to_wt_value() }).collect();
+        jsonobj.insert("schemes".to_string(), Value::Array(schemes));
+        serde_json::to_string_pretty(&jsonobj).unwrap()
+    }
+
+    /// Merge into a `settings.json`, replacing the schemes of the same name and appending the others.
+    /// The text is edited in place, so comments and formatting are kept.
+    pub fn merge_wt(&self, settings: &str, profile: Option<&str>) -> Result<String, SchemeError> {
+        let ctx = ErrorContext::new(SchemeFormat::WindowsTerminal);
+        if !parse_jsonc(&ctx, settings)?.is_object() {
+            return Err(ctx.syntax("settings must be a JSON object", None));
         }
-        let schemes = match kv.get("schemes") {
+        let root = jsonc::scan(settings);
+        let mut edits = Edits::new(settings);
+        // When several schemes share a name, the last one wins
+        let schemes: Vec<&ColorScheme> = self.0.iter().enumerate()
+            .filter(|(id, schm)| !self.0[id + 1..].iter().any(|other| other.name == schm.name))
+            .map(|(_, schm)| schm)
+            .collect();
+        match root.get("schemes") {
+            Some(node @ Node { kind: NodeKind::Array(existing), .. }) => {
+                let mut appended = Vec::new();
+                for schm in schemes {
+                    match existing.iter().find(|s| s.get("name").and_then(Node::as_str) == Some(schm.name.as_str())) {
+                        Some(existing) => { edits.replace(existing, &schm.to_wt_value()); }
+                        None => { appended.push((None, schm.to_wt_value())); }
+                    }
+                }
+                edits.append(node, &appended);
+            }
+            Some(other) => { return Err(ctx.invalid("schemes", &settings[other.start..other.end])); }
             None => {
-                return Err(SchemeError::Invalid);
+                let schemes = schemes.iter().map(|schm| schm.to_wt_value()).collect();
+                edits.append(&root, &[(Some("schemes"), Value::Array(schemes))]);
             }
-            Some(schm) => { schm }
-        };
-        if !schemes.is_array() {
-            return Err(SchemeError::Invalid);
         }
-        let schemes = match schemes.as_array() {
-            None => {
-                return Err(SchemeError::Invalid);
+        if let Some(profile) = profile {
+            let scheme_name = Value::String(self.0.first().ok_or(SchemeError::NoSchemes)?.name.clone());
+            let target = find_wt_profile(&root, profile)
+                .ok_or_else(|| SchemeError::NoSuchProfile(profile.to_string()))?;
+            match target.get("colorScheme") {
+                Some(existing) => { edits.replace(existing, &scheme_name); }
+                None => { edits.append(target, &[(Some("colorScheme"), scheme_name)]); }
             }
-            Some(schm) => { schm }
-        };
+        }
+        Ok(edits.apply())
+    }
 
-        let get_u32 = |schm: &Value, k: &str| { u32::from_str_radix(&schm.get(k).unwrap().as_str().unwrap()[1..], 16).unwrap() };
-        let schemes = schemes.iter()
-            .map(|schm| {
-                ColorScheme {
-                    name: schm.get("name").unwrap().as_str().unwrap().to_string(),
-                    black: get_u32(schm, "black"),
-                    red: get_u32(schm, "red"),
-                    green: get_u32(schm, "green"),
-                    yellow: get_u32(schm, "yellow"),
-                    blue: get_u32(schm, "blue"),
-                    magenta: get_u32(schm, "purple"),
-                    cyan: get_u32(schm, "cyan"),
-                    white: get_u32(schm, "white"),
-                    bright_black: get_u32(schm, "brightBlack"),
-                    bright_red: get_u32(schm, "brightRed"),
-                    bright_green: get_u32(schm, "brightGreen"),
-                    bright_yellow: get_u32(schm, "brightYellow"),
-                    bright_blue: get_u32(schm, "brightBlue"),
-                    bright_magenta: get_u32(schm, "brightPurple"),
-                    bright_cyan: get_u32(schm, "brightCyan"),
-                    bright_white: get_u32(schm, "brightWhite"),
-                    background: get_u32(schm, "background"),
-                    foreground: get_u32(schm, "foreground"),
+    /// Read a `settings.json` or `defaults.json`, an array of schemes or a single scheme object.
+    /// Comments and trailing commas are accepted like Windows Terminal does.
+    pub fn from_wt(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let ctx = ErrorContext::new(SchemeFormat::WindowsTerminal);
+        let root = parse_jsonc(&ctx, s)?;
+        let (path, schemes) = match &root {
+            Value::Array(schemes) => { ("", schemes.as_slice()) }
+            Value::Object(obj) if obj.contains_key("schemes") => {
+                match &obj["schemes"] {
+                    Value::Array(schemes) => { ("schemes", schemes.as_slice()) }
+                    other => { return Err(ctx.invalid("schemes", &other.to_string())); }
                 }
-            }).collect();
-        Ok(schemes)
+            }
+            // A bare scheme, as pasted from a scheme gallery
+            Value::Object(obj) if obj.contains_key("name") => { ("", std::slice::from_ref(&root)) }
+            Value::Object(_) => { return Err(ctx.missing("schemes")); }
+            _ => { return Err(ctx.syntax("expected a settings object, a scheme or an array of schemes", None)); }
+        };
+
+        schemes.iter()
+            .enumerate()
+            .map(|(id, schm)| {
+                let name = match schm.get("name").and_then(|n| n.as_str()) {
+                    None => { return Err(ctx.missing(&format!("{}[{}].name", path, id))); }
+                    Some(name) => { name }
+                };
+                let ctx = ctx.scheme(name);
+                let get_color = |k: &str| { ctx.color(k, schm.get(k).and_then(|v| v.as_str()), Color::parse) };
+                let get_opt = |k: &str| { ctx.optional_color(k, schm.get(k).and_then(|v| v.as_str()), Color::parse) };
+                Ok(ColorScheme {
+                    name: name.to_string(),
+                    author: None,
+                    black: get_color("black")?,
+                    red: get_color("red")?,
+                    green: get_color("green")?,
+                    yellow: get_color("yellow")?,
+                    blue: get_color("blue")?,
+                    magenta: get_color("purple")?,
+                    cyan: get_color("cyan")?,
+                    white: get_color("white")?,
+                    bright_black: get_color("brightBlack")?,
+                    bright_red: get_color("brightRed")?,
+                    bright_green: get_color("brightGreen")?,
+                    bright_yellow: get_color("brightYellow")?,
+                    bright_blue: get_color("brightBlue")?,
+                    bright_magenta: get_color("brightPurple")?,
+                    bright_cyan: get_color("brightCyan")?,
+                    bright_white: get_color("brightWhite")?,
+                    background: get_color("background")?,
+                    foreground: get_color("foreground")?,
+                    cursor: get_opt("cursorColor")?,
+                    cursor_text: None,
+                    selection_background: get_opt("selectionBackground")?,
+                    selection_foreground: None,
+                    bold: None,
+                })
+            }).collect()
     }
 
     pub fn from_alacritty(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
-        let kv: Result<BTreeMap<String, serde_yaml::Value>, SchemeError> = serde_yaml::from_str(s).map_err(|_| SchemeError::Invalid);
+        let ctx = ErrorContext::new(SchemeFormat::Alacritty);
+        let kv: Result<BTreeMap<String, serde_yaml::Value>, serde_yaml::Error> = serde_yaml::from_str(s);
         let kv = match kv {
             Ok(map) => { map }
-            Err(_) => { return Err(SchemeError::Invalid); }
+            Err(e) => {
+                let location = e.location().map(|l| Location { line: l.line(), column: l.column() });
+                return Err(ctx.syntax(&e, location));
+            }
         };
-        let scheme = match kv.get("colors") {
+        let colors = match kv.get("colors") {
             None => {
-                return Err(SchemeError::Invalid);
+                return Err(ctx.missing("colors"));
             }
             Some(schm) => { schm }
         };
-        let get_u32 = |schm: &serde_yaml::Value, k: &str| {
-            if k == "foreground" {
-                let color_str = &schm.get("primary").unwrap().as_mapping().unwrap()
-                    .get(&serde_yaml::Value::from("foreground")).unwrap().as_str().unwrap()[1..];
-                return u32::from_str_radix(color_str, 16).unwrap();
-            } else if k == "background" {
-                let color_str = &schm.get("primary").unwrap().as_mapping().unwrap()
-                    .get(&serde_yaml::Value::from("background")).unwrap().as_str().unwrap()[1..];
-                return u32::from_str_radix(color_str, 16).unwrap();
-            }
-            let (typ, key) =
-                if let Some(stripped) = k.strip_prefix("bright_") {
-                    ("bright", stripped)
-                } else {
-                    ("normal", k)
-                };
-            let color_str = &schm.get(typ)
-                .unwrap().as_mapping().unwrap().get(&serde_yaml::Value::from(key)).unwrap().as_str().unwrap()[1..];
-            u32::from_str_radix(color_str, 16).unwrap()
-        };
-        let scheme =
-            ColorScheme {
-                name: "default".to_string(),
-                black: get_u32(scheme, "black"),
-                red: get_u32(scheme, "red"),
-                green: get_u32(scheme, "green"),
-                yellow: get_u32(scheme, "yellow"),
-                blue: get_u32(scheme, "blue"),
-                magenta: get_u32(scheme, "magenta"),
-                cyan: get_u32(scheme, "cyan"),
-                white: get_u32(scheme, "white"),
-                bright_black: get_u32(scheme, "bright_black"),
-                bright_red: get_u32(scheme, "bright_red"),
-                bright_green: get_u32(scheme, "bright_green"),
-                bright_yellow: get_u32(scheme, "bright_yellow"),
-                bright_blue: get_u32(scheme, "bright_blue"),
-                bright_magenta: get_u32(scheme, "bright_magenta"),
-                bright_cyan: get_u32(scheme, "bright_cyan"),
-                bright_white: get_u32(scheme, "bright_white"),
-                background: get_u32(scheme, "background"),
-                foreground: get_u32(scheme, "foreground"),
-            };
-        let schemes = ColorSchemes::new(vec![scheme]);
-        Ok(Box::from(schemes))
-    }
-
-    pub fn to_alacritty(&self) -> Box<String> {
-        let res: Vec<String> = self.0.clone().into_iter().map(|schm| {
-            let yaml_str = |s: String| { serde_yaml::Value::String(s) };
-            let mut primary = serde_yaml::mapping::Mapping::with_capacity(2);
-            primary.insert(yaml_str("foreground".to_string()), yaml_str(schm.foreground.to_hex_repr()));
-            primary.insert(yaml_str("background".to_string()), yaml_str(schm.background.to_hex_repr()));
-            let mut normal = serde_yaml::mapping::Mapping::with_capacity(8);
-            normal.insert(yaml_str("black".to_string()), yaml_str(schm.black.to_hex_repr()));
-            normal.insert(yaml_str("red".to_string()), yaml_str(schm.red.to_hex_repr()));
-            normal.insert(yaml_str("green".to_string()), yaml_str(schm.green.to_hex_repr()));
-            normal.insert(yaml_str("yellow".to_string()), yaml_str(schm.yellow.to_hex_repr()));
-            normal.insert(yaml_str("blue".to_string()), yaml_str(schm.blue.to_hex_repr()));
-            normal.insert(yaml_str("magenta".to_string()), yaml_str(schm.magenta.to_hex_repr()));
-            normal.insert(yaml_str("cyan".to_string()), yaml_str(schm.cyan.to_hex_repr()));
-            normal.insert(yaml_str("white".to_string()), yaml_str(schm.white.to_hex_repr()));
-            let mut bright = serde_yaml::mapping::Mapping::with_capacity(8);
-            bright.insert(yaml_str("black".to_string()), yaml_str(schm.bright_black.to_hex_repr()));
-            bright.insert(yaml_str("red".to_string()), yaml_str(schm.bright_red.to_hex_repr()));
-            bright.insert(yaml_str("green".to_string()), yaml_str(schm.bright_green.to_hex_repr()));
-            bright.insert(yaml_str("yellow".to_string()), yaml_str(schm.bright_yellow.to_hex_repr()));
-            bright.insert(yaml_str("blue".to_string()), yaml_str(schm.bright_blue.to_hex_repr()));
-            bright.insert(yaml_str("magenta".to_string()), yaml_str(schm.bright_magenta.to_hex_repr()));
-            bright.insert(yaml_str("cyan".to_string()), yaml_str(schm.bright_cyan.to_hex_repr()));
-            bright.insert(yaml_str("white".to_string()), yaml_str(schm.bright_white.to_hex_repr()));
-            let mut color = serde_yaml::mapping::Mapping::new();
-            color.insert(yaml_str("primary".to_string()), serde_yaml::Value::Mapping(primary));
-            color.insert(yaml_str("normal".to_string()), serde_yaml::Value::Mapping(normal));
-            color.insert(yaml_str("bright".to_string()), serde_yaml::Value::Mapping(bright));
+        let scheme = read_alacritty(&ctx, |typ, k| colors.get(typ).and_then(|t| t.get(k)).and_then(|v| v.as_str()))?;
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_alacritty(&self) -> String {
+        let res: Vec<String> = self.0.iter().map(|schm| {
+            let yaml_str = |s: &str| { serde_yaml::Value::String(s.to_string()) };
+            let colors: serde_yaml::mapping::Mapping = alacritty_tables(schm).into_iter()
+                .map(|(table, colors)| {
+                    let colors = colors.iter().map(|(k, c)| (yaml_str(k), yaml_str(&c.to_hex()))).collect();
+                    (yaml_str(table), serde_yaml::Value::Mapping(colors))
+                })
+                .collect();
             let mut root = serde_yaml::mapping::Mapping::new();
-            root.insert(yaml_str("color".to_string()), serde_yaml::Value::Mapping(color));
+            root.insert(yaml_str("colors"), serde_yaml::Value::Mapping(colors));
             serde_yaml::to_string(&root).unwrap()
         }).collect();
-        Box::new(res.join(""))
+        res.join("")
+    }
+
+    pub fn from_alacritty_toml(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let ctx = ErrorContext::new(SchemeFormat::AlacrittyToml);
+        let root: toml::Value = toml::from_str(s).map_err(|e| toml_syntax_error(&ctx, e))?;
+        let colors = match root.get("colors") {
+            None => { return Err(ctx.missing("colors")); }
+            Some(colors) => { colors }
+        };
+        let scheme = read_alacritty(&ctx, |typ, k| colors.get(typ).and_then(|t| t.get(k)).and_then(|v| v.as_str()))?;
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_alacritty_toml(&self) -> String {
+        let res: Vec<String> = self.0.iter().map(|schm| {
+            let colors: toml::value::Table = alacritty_tables(schm).into_iter()
+                .map(|(table, colors)| {
+                    let colors = colors.iter().map(|(k, c)| (k.to_string(), toml::Value::String(c.to_hex()))).collect();
+                    (table.to_string(), toml::Value::Table(colors))
+                })
+                .collect();
+            let mut root = toml::value::Table::new();
+            root.insert("colors".to_string(), toml::Value::Table(colors));
+            toml::to_string(&toml::Value::Table(root)).unwrap()
+        }).collect();
+        res.join("")
     }
 
     pub fn from_xshell(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
-        let conf = ini::Ini::load_from_str(s).unwrap();
-        // Directly read and filter invalid sections & `Names` section, rather than reading `Names` section.
-        // This isn't orthodox but should be more fault-acceptable
-        let get_u32 = |schm: &Properties, k: &str| {
+        let ctx = ErrorContext::new(SchemeFormat::XShell);
+        let conf = ini::Ini::load_from_str(s).map_err(|e| ini_syntax_error(&ctx, e))?;
+        let get_color = |ctx: &ErrorContext, schm: &Properties, k: &str| {
             match schm.get(k) {
-                None => {0}
+                None => { Ok(Color::rgb(0, 0, 0)) }
                 Some(val) => {
-                    u32::from_str_radix(val, 16).unwrap()
+                    Color::parse(val).ok_or_else(|| ctx.invalid(k, val))
                 }
             }
         };
-        let sections: Vec<ColorScheme> = conf.sections()
-            .flatten()
-            .filter(|name| { !name.eq_ignore_ascii_case("Names") })
-            .map(|name| {
-                (name, conf.section(Some(name)).unwrap())
+        let read_scheme = |name: &str, section: &Properties| {
+            let ctx = ctx.scheme(name);
+            Ok(ColorScheme {
+                name: name.to_string(),
+                author: None,
+                black: get_color(&ctx, section, "black")?,
+                red: get_color(&ctx, section, "red")?,
+                green: get_color(&ctx, section, "green")?,
+                yellow: get_color(&ctx, section, "yellow")?,
+                blue: get_color(&ctx, section, "blue")?,
+                magenta: get_color(&ctx, section, "magenta")?,
+                cyan: get_color(&ctx, section, "cyan")?,
+                white: get_color(&ctx, section, "white")?,
+                bright_black: get_color(&ctx, section, "black(bold)")?,
+                bright_red: get_color(&ctx, section, "red(bold)")?,
+                bright_green: get_color(&ctx, section, "green(bold)")?,
+                bright_yellow: get_color(&ctx, section, "yellow(bold)")?,
+                bright_blue: get_color(&ctx, section, "blue(bold)")?,
+                bright_magenta: get_color(&ctx, section, "magenta(bold)")?,
+                bright_cyan: get_color(&ctx, section, "cyan(bold)")?,
+                bright_white: get_color(&ctx, section, "white(bold)")?,
+                background: get_color(&ctx, section, "background")?,
+                foreground: get_color(&ctx, section, "text")?,
+                cursor: None,
+                cursor_text: None,
+                selection_background: None,
+                selection_foreground: None,
+                bold: ctx.optional_color("text(bold)", section.get("text(bold)"), Color::parse)?,
             })
-            .map(|(name, section)| {
-                ColorScheme {
-                    name: name.to_string(),
-                    black: get_u32(section, "black"),
-                    red: get_u32(section, "red"),
-                    green: get_u32(section, "green"),
-                    yellow: get_u32(section, "yellow"),
-                    blue: get_u32(section, "blue"),
-                    magenta: get_u32(section, "magenta"),
-                    cyan: get_u32(section, "cyan"),
-                    white: get_u32(section, "white"),
-                    bright_black: get_u32(section, "black(bold)"),
-                    bright_red: get_u32(section, "red(bold)"),
-                    bright_green: get_u32(section, "green(bold)"),
-                    bright_yellow: get_u32(section, "yellow(bold)"),
-                    bright_blue: get_u32(section, "blue(bold)"),
-                    bright_magenta: get_u32(section, "magenta(bold)"),
-                    bright_cyan: get_u32(section, "cyan(bold)"),
-                    bright_white: get_u32(section, "white(bold)"),
-                    background: get_u32(section, "background"),
-                    foreground: get_u32(section, "text"),
-                }
-            })
-            .collect();
-        Ok(Box::new(ColorSchemes(sections)))
+        };
+        let sections: Result<Vec<ColorScheme>, SchemeError> = match xshell_names(&conf) {
+            // XShell lists `count` names, the highest index being the first scheme
+            Some(names) => {
+                names.iter()
+                    .map(|name| {
+                        let section = conf.section(Some(name.as_str()))
+                            .ok_or_else(|| ctx.scheme(name).missing(&format!("[{}]", name)))?;
+                        read_scheme(name, section)
+                    })
+                    .collect()
+            }
+            // Without a usable `Names` section, every other section is a scheme
+            None => {
+                conf.iter()
+                    .filter_map(|(name, section)| { name.map(|name| (name, section)) })
+                    .filter(|(name, _)| { !name.eq_ignore_ascii_case("Names") })
+                    .map(|(name, section)| read_scheme(name, section))
+                    .collect()
+            }
+        };
+        Ok(Box::new(ColorSchemes(sections?)))
     }
 
-    pub fn to_xshell(&self) -> Box<String> {
+    pub fn to_xshell(&self) -> String {
         let gcss = &self.0;
         let size = gcss.len();
         let mut names = Vec::new();
         let mut res: String = gcss.iter().map(|schm| {
             names.push(schm.name.clone());
             format!("[{name}]
-text={foreground:06x}
-cyan(bold)={bright_cyan:06x}
-text(bold)={foreground:06x}
-magenta={magenta:06x}
-green={green:06x}
-green(bold)={bright_green:06x}
-background={background:06x}
-cyan={cyan:06x}
-red(bold)={bright_red:06x}
-yellow={yellow:06x}
-magenta(bold)={bright_magenta:06x}
-yellow(bold)={bright_yellow:06x}
-red={red:06x}
-white={white:06x}
-blue(bold)={bright_blue:06x}
-white(bold)={bright_white:06x}
-black={black:06x}
-blue={blue:06x}
-black(bold)={bright_black:06x}",
+text={foreground:x}
+cyan(bold)={bright_cyan:x}
+text(bold)={bold:x}
+magenta={magenta:x}
+green={green:x}
+green(bold)={bright_green:x}
+background={background:x}
+cyan={cyan:x}
+red(bold)={bright_red:x}
+yellow={yellow:x}
+magenta(bold)={bright_magenta:x}
+yellow(bold)={bright_yellow:x}
+red={red:x}
+white={white:x}
+blue(bold)={bright_blue:x}
+white(bold)={bright_white:x}
+black={black:x}
+blue={blue:x}
+black(bold)={bright_black:x}",
                     name = schm.name.as_str(),
                     black = schm.black,
                     red = schm.red,
@@ -330,44 +402,962 @@ black(bold)={bright_black:06x}",
                     bright_white = schm.bright_white,
                     background = schm.background,
                     foreground = schm.foreground,
+                    bold = schm.bold.unwrap_or(schm.foreground),
             )
         }).collect::<Vec<String>>().join("\n");
         let mut name_buf = vec!["\n[Names]".to_string()];
         for (id, name) in names.iter().enumerate() {
-            name_buf.push(format!("name{}={}", size - id, name.as_str()));
+            name_buf.push(format!("name{}={}", size - 1 - id, name.as_str()));
         }
-        name_buf.push(format!("count={count}", count = size));
+        name_buf.push(format!("count={count}\n", count = size));
         let name_buf = name_buf.join("\n");
         res.push_str(&name_buf);
-        Box::new(res)
+        res
+    }
+
+    pub fn from_securecrt(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        // SecureCRT keeps the 16 ANSI colors in a `B:"ANSI Color RGB"` blob and every named scheme
+        // in `Color Schemes.ini` as an 8-byte foreground/background blob. Entries are read in order,
+        // so a named blob picks up the last ANSI palette seen before it.
+        let ctx = ErrorContext::new(SchemeFormat::SecureCRT);
+        let entries = parse_securecrt_entries(&ctx, s)?;
+        // Session files also hold other blobs, such as fonts. `Color Schemes.ini` only holds schemes.
+        let session = entries.iter().any(|(typ, key, _)| *typ != 'B' || key == "ANSI Color RGB");
+        let mut palette: Option<[Color; 16]> = None;
+        let mut scheme_name: Option<String> = None;
+        let mut schemes = Vec::new();
+        for (typ, key, value) in entries {
+            match (typ, value) {
+                ('S', SecureCRTValue::Str(val)) if key == "Color Scheme" => {
+                    scheme_name = Some(val);
+                }
+                ('B', SecureCRTValue::Blob(bytes)) if key == "ANSI Color RGB" => {
+                    let colors = securecrt_colors(&bytes).try_into()
+                        .ok()
+                        .filter(|_| bytes.len() == 64)
+                        .ok_or_else(|| ctx.invalid(&key, &format!("{} bytes", bytes.len())))?;
+                    palette = Some(colors);
+                }
+                ('B', SecureCRTValue::Blob(bytes)) if bytes.len() == 8 => {
+                    // `Color Schemes.ini` alone has no ANSI colors
+                    let ansi = palette.ok_or_else(|| ctx.scheme(&key).missing("ANSI Color RGB"))?;
+                    let colors = securecrt_colors(&bytes);
+                    schemes.push(ColorScheme::from_palette(key, ansi, colors[0], colors[1]));
+                }
+                ('B', SecureCRTValue::Blob(bytes)) if !session => {
+                    return Err(ctx.scheme(&key).invalid(&key, &format!("{} bytes", bytes.len())));
+                }
+                _ => {}
+            }
+        }
+        // A session file only carries the ANSI palette.
+        if schemes.is_empty() {
+            let ansi = match palette {
+                None => { return Err(ctx.missing("ANSI Color RGB")); }
+                Some(ansi) => { ansi }
+            };
+            let name = scheme_name.unwrap_or_else(|| "default".to_string());
+            schemes.push(ColorScheme::from_palette(name, ansi, ansi[7], ansi[0]));
+        }
+        Ok(Box::new(ColorSchemes(schemes)))
+    }
+
+    /// Session entries. The foreground and background belong to the color scheme the session
+    /// names, see `to_securecrt_schemes`.
+    pub fn to_securecrt(&self) -> String {
+        self.0.iter().map(|schm| {
+            let ansi = schm.ansi();
+            let mut lines = vec![
+                format!("S:\"Color Scheme\"={}", schm.name),
+                format!("B:\"ANSI Color RGB\"={:08x}", ansi.len() * 4),
+            ];
+            for row in ansi.chunks(4) {
+                lines.push(securecrt_blob_line(row));
+            }
+            lines.join("\n")
+        }).collect::<Vec<String>>().join("\n") + "\n"
+    }
+
+    /// `Color Schemes.ini` entries, a foreground/background pair per scheme
+    pub fn to_securecrt_schemes(&self) -> String {
+        self.0.iter().map(|schm| {
+            // Keys cannot hold quotes, SecureCRT has no escape for them
+            format!("B:\"{}\"={:08x}\n{}\n", schm.name.replace('"', "'"), 8,
+                    securecrt_blob_line(&[schm.foreground, schm.background]))
+        }).collect()
+    }
+
+    pub fn from_mobaxterm(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let ctx = ErrorContext::new(SchemeFormat::MobaXTerm);
+        let conf = ini::Ini::load_from_str(s).map_err(|e| ini_syntax_error(&ctx, e))?;
+        let section = match conf.section(Some("Colors")) {
+            None => { return Err(ctx.missing("[Colors]")); }
+            Some(section) => { section }
+        };
+        let get_color = |k: &str| -> Result<Color, SchemeError> {
+            ctx.color(&format!("Colors.{}", k), section.get(k), Color::parse)
+        };
+        let get_opt = |k: &str| -> Result<Option<Color>, SchemeError> {
+            ctx.optional_color(&format!("Colors.{}", k), section.get(k), Color::parse)
+        };
+        let scheme = ColorScheme {
+            name: "default".to_string(),
+            author: None,
+            black: get_color("Black")?,
+            red: get_color("Red")?,
+            green: get_color("Green")?,
+            yellow: get_color("Yellow")?,
+            blue: get_color("Blue")?,
+            magenta: get_color("Magenta")?,
+            cyan: get_color("Cyan")?,
+            white: get_color("White")?,
+            bright_black: get_color("BoldBlack")?,
+            bright_red: get_color("BoldRed")?,
+            bright_green: get_color("BoldGreen")?,
+            bright_yellow: get_color("BoldYellow")?,
+            bright_blue: get_color("BoldBlue")?,
+            bright_magenta: get_color("BoldMagenta")?,
+            bright_cyan: get_color("BoldCyan")?,
+            bright_white: get_color("BoldWhite")?,
+            background: get_color("BackgroundColour")?,
+            foreground: get_color("ForegroundColour")?,
+            cursor: get_opt("CursorColour")?,
+            cursor_text: None,
+            selection_background: None,
+            selection_foreground: None,
+            bold: get_opt("BoldColour")?,
+        };
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_mobaxterm(&self) -> String {
+        self.0.iter().map(|schm| {
+            format!("[Colors]
+DefaultColorScheme=0
+BoldColour={bold}
+BackgroundColour={background}
+ForegroundColour={foreground}
+CursorColour={cursor}
+Black={black}
+Red={red}
+Green={green}
+Yellow={yellow}
+Blue={blue}
+Magenta={magenta}
+Cyan={cyan}
+White={white}
+BoldBlack={bright_black}
+BoldRed={bright_red}
+BoldGreen={bright_green}
+BoldYellow={bright_yellow}
+BoldBlue={bright_blue}
+BoldMagenta={bright_magenta}
+BoldCyan={bright_cyan}
+BoldWhite={bright_white}
+",
+                    black = schm.black.to_decimal(),
+                    red = schm.red.to_decimal(),
+                    green = schm.green.to_decimal(),
+                    yellow = schm.yellow.to_decimal(),
+                    blue = schm.blue.to_decimal(),
+                    magenta = schm.magenta.to_decimal(),
+                    cyan = schm.cyan.to_decimal(),
+                    white = schm.white.to_decimal(),
+                    bright_black = schm.bright_black.to_decimal(),
+                    bright_red = schm.bright_red.to_decimal(),
+                    bright_green = schm.bright_green.to_decimal(),
+                    bright_yellow = schm.bright_yellow.to_decimal(),
+                    bright_blue = schm.bright_blue.to_decimal(),
+                    bright_magenta = schm.bright_magenta.to_decimal(),
+                    bright_cyan = schm.bright_cyan.to_decimal(),
+                    bright_white = schm.bright_white.to_decimal(),
+                    background = schm.background.to_decimal(),
+                    foreground = schm.foreground.to_decimal(),
+                    bold = schm.bold.unwrap_or(schm.foreground).to_decimal(),
+                    cursor = schm.cursor.unwrap_or(schm.foreground).to_decimal(),
+            )
+        }).collect::<Vec<String>>().join("")
+    }
+
+    pub fn from_iterm2(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let ctx = ErrorContext::new(SchemeFormat::ITerm2);
+        let root = plist::Value::from_reader_xml(s.as_bytes()).map_err(|e| ctx.syntax(&e, None))?;
+        let dict = match root.as_dictionary() {
+            None => { return Err(ctx.syntax("root element must be a dict", None)); }
+            Some(dict) => { dict }
+        };
+        // Each color is a dict of float components in [0, 1], in sRGB unless `Color Space` says P3.
+        // `Calibrated` components depend on the display they were picked on and are refused.
+        let get_color = |k: &str| -> Result<Color, SchemeError> {
+            let color = dict.get(k).ok_or_else(|| ctx.missing(k))?;
+            let color = color.as_dictionary().ok_or_else(|| ctx.invalid(k, &format!("{:?}", color)))?;
+            let component = |c: &str| -> Result<f64, SchemeError> {
+                let path = format!("{}.{}", k, c);
+                let val = match color.get(c) {
+                    Some(plist::Value::Real(f)) => { *f }
+                    Some(plist::Value::Integer(i)) => {
+                        i.as_signed().ok_or_else(|| ctx.invalid(&path, &i.to_string()))? as f64
+                    }
+                    Some(other) => { return Err(ctx.invalid(&path, &format!("{:?}", other))); }
+                    None => { return Err(ctx.missing(&path)); }
+                };
+                Ok(val)
+            };
+            let (r, g, b) = (component("Red Component")?, component("Green Component")?, component("Blue Component")?);
+            match color.get("Color Space").and_then(|space| space.as_string()) {
+                None | Some("sRGB") => { Ok(Color::from_f64(r, g, b)) }
+                Some("P3") => { Ok(Color::from_display_p3(r, g, b)) }
+                Some(space) => { Err(ctx.invalid(&format!("{}.Color Space", k), space)) }
+            }
+        };
+        let get_opt = |k: &str| -> Result<Option<Color>, SchemeError> {
+            if dict.contains_key(k) { get_color(k).map(Some) } else { Ok(None) }
+        };
+        let ansi = read_ansi(|i| get_color(&format!("Ansi {} Color", i)))?;
+        let scheme = ColorScheme {
+            cursor: get_opt("Cursor Color")?,
+            cursor_text: get_opt("Cursor Text Color")?,
+            selection_background: get_opt("Selection Color")?,
+            selection_foreground: get_opt("Selected Text Color")?,
+            bold: get_opt("Bold Color")?,
+            ..ColorScheme::from_palette("default".to_string(), ansi, get_color("Foreground Color")?, get_color("Background Color")?)
+        };
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_iterm2(&self) -> String {
+        let color = |c: Color| {
+            let mut dict = plist::Dictionary::new();
+            let (r, g, b) = c.to_f64();
+            dict.insert("Alpha Component".to_string(), plist::Value::Real(1.0));
+            dict.insert("Blue Component".to_string(), plist::Value::Real(b));
+            dict.insert("Color Space".to_string(), plist::Value::String("sRGB".to_string()));
+            dict.insert("Green Component".to_string(), plist::Value::Real(g));
+            dict.insert("Red Component".to_string(), plist::Value::Real(r));
+            plist::Value::Dictionary(dict)
+        };
+        self.0.iter().map(|schm| {
+            // Keys sorted the way iTerm2 exports them
+            let mut colors: Vec<(String, Color)> = schm.ansi().iter().enumerate()
+                .map(|(i, c)| (format!("Ansi {} Color", i), *c))
+                .collect();
+            colors.push(("Background Color".to_string(), schm.background));
+            colors.push(("Bold Color".to_string(), schm.bold.unwrap_or(schm.foreground)));
+            colors.push(("Cursor Color".to_string(), schm.cursor.unwrap_or(schm.foreground)));
+            colors.push(("Cursor Text Color".to_string(), schm.cursor_text.unwrap_or(schm.background)));
+            colors.push(("Foreground Color".to_string(), schm.foreground));
+            colors.push(("Selected Text Color".to_string(), schm.selection_foreground.unwrap_or(schm.background)));
+            colors.push(("Selection Color".to_string(), schm.selection_background.unwrap_or(schm.foreground)));
+            colors.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut root = plist::Dictionary::new();
+            for (k, c) in colors {
+                root.insert(k, color(c));
+            }
+            let mut buf = Vec::new();
+            plist::Value::Dictionary(root).to_writer_xml(&mut buf).unwrap();
+            String::from_utf8(buf).unwrap() + "\n"
+        }).collect::<Vec<String>>().join("")
+    }
+
+    pub fn from_kitty(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let mut name = "default".to_string();
+        let mut author = None;
+        let mut kv = HashMap::<&str, &str>::new();
+        for line in s.lines().map(|l| l.trim()) {
+            // Metadata header understood by the themes kitten
+            if let Some(meta) = line.strip_prefix("## name:") {
+                name = meta.trim().to_string();
+                continue;
+            }
+            if let Some(meta) = line.strip_prefix("## author:") {
+                author = Some(meta.trim().to_string());
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once(char::is_whitespace) {
+                None => { continue; }
+                Some(kv) => { kv }
+            };
+            if key == "include" {
+                continue;
+            }
+            kv.insert(key, value.trim());
+        }
+        let ctx = ErrorContext::new(SchemeFormat::Kitty).scheme(&name);
+        let get_color = |k: &str| -> Result<Color, SchemeError> {
+            ctx.color(k, kv.get(k).copied(), Color::parse)
+        };
+        // `none` and `background` ask kitty to use the cell colors
+        let get_opt = |k: &str| -> Result<Option<Color>, SchemeError> {
+            ctx.optional_color(k, kv.get(k).copied().filter(|v| !matches!(*v, "none" | "background")), Color::parse)
+        };
+        let ansi = read_ansi(|i| get_color(&format!("color{}", i)))?;
+        let scheme = ColorScheme {
+            cursor: get_opt("cursor")?,
+            cursor_text: get_opt("cursor_text_color")?,
+            selection_background: get_opt("selection_background")?,
+            selection_foreground: get_opt("selection_foreground")?,
+            author,
+            ..ColorScheme::from_palette(name, ansi, get_color("foreground")?, get_color("background")?)
+        };
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_kitty(&self) -> String {
+        self.0.iter().map(|schm| {
+            format!("## name: {name}
+{author}
+foreground {foreground}
+background {background}
+cursor {cursor}
+cursor_text_color {cursor_text}
+selection_foreground {selection_foreground}
+selection_background {selection_background}
+
+color0 {black}
+color8 {bright_black}
+color1 {red}
+color9 {bright_red}
+color2 {green}
+color10 {bright_green}
+color3 {yellow}
+color11 {bright_yellow}
+color4 {blue}
+color12 {bright_blue}
+color5 {magenta}
+color13 {bright_magenta}
+color6 {cyan}
+color14 {bright_cyan}
+color7 {white}
+color15 {bright_white}
+",
+                    name = schm.name.as_str(),
+                    author = schm.author.as_ref().map(|author| format!("## author: {}\n", author)).unwrap_or_default(),
+                    black = schm.black.to_hex(),
+                    red = schm.red.to_hex(),
+                    green = schm.green.to_hex(),
+                    yellow = schm.yellow.to_hex(),
+                    blue = schm.blue.to_hex(),
+                    magenta = schm.magenta.to_hex(),
+                    cyan = schm.cyan.to_hex(),
+                    white = schm.white.to_hex(),
+                    bright_black = schm.bright_black.to_hex(),
+                    bright_red = schm.bright_red.to_hex(),
+                    bright_green = schm.bright_green.to_hex(),
+                    bright_yellow = schm.bright_yellow.to_hex(),
+                    bright_blue = schm.bright_blue.to_hex(),
+                    bright_magenta = schm.bright_magenta.to_hex(),
+                    bright_cyan = schm.bright_cyan.to_hex(),
+                    bright_white = schm.bright_white.to_hex(),
+                    background = schm.background.to_hex(),
+                    foreground = schm.foreground.to_hex(),
+                    cursor = schm.cursor.unwrap_or(schm.foreground).to_hex(),
+                    cursor_text = schm.cursor_text.unwrap_or(schm.background).to_hex(),
+                    selection_foreground = schm.selection_foreground.unwrap_or(schm.background).to_hex(),
+                    selection_background = schm.selection_background.unwrap_or(schm.foreground).to_hex(),
+            )
+        }).collect::<Vec<String>>().join("\n")
+    }
+
+    pub fn from_wezterm(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let ctx = ErrorContext::new(SchemeFormat::WezTerm);
+        let root: toml::Value = toml::from_str(s).map_err(|e| toml_syntax_error(&ctx, e))?;
+        let colors = match root.get("colors") {
+            None => { return Err(ctx.missing("colors")); }
+            Some(colors) => { colors }
+        };
+        let metadata = |k: &str| { root.get("metadata").and_then(|meta| meta.get(k)).and_then(|v| v.as_str()) };
+        let name = metadata("name").unwrap_or("default").to_string();
+        let ctx = ctx.scheme(&name);
+        let get_color = |k: &str| -> Result<Color, SchemeError> {
+            ctx.color(&format!("colors.{}", k), colors.get(k).and_then(|v| v.as_str()), Color::parse)
+        };
+        let get_opt = |k: &str| -> Result<Option<Color>, SchemeError> {
+            ctx.optional_color(&format!("colors.{}", k), colors.get(k).and_then(|v| v.as_str()), Color::parse)
+        };
+        let get_palette = |k: &str| -> Result<Vec<Color>, SchemeError> {
+            let path = format!("colors.{}", k);
+            let palette = colors.get(k).ok_or_else(|| ctx.missing(&path))?;
+            let palette = match palette.as_array() {
+                Some(palette) if palette.len() == 8 => { palette }
+                _ => { return Err(ctx.invalid(&path, &palette.to_string())); }
+            };
+            palette.iter()
+                .enumerate()
+                .map(|(i, c)| ctx.color(&format!("{}[{}]", path, i), c.as_str(), Color::parse))
+                .collect()
+        };
+        let normal = get_palette("ansi")?;
+        let brights = get_palette("brights")?;
+        let ansi = read_ansi(|i| Ok(if i < 8 { normal[i] } else { brights[i - 8] }))?;
+        let scheme = ColorScheme {
+            cursor: get_opt("cursor_bg")?,
+            cursor_text: get_opt("cursor_fg")?,
+            selection_background: get_opt("selection_bg")?,
+            selection_foreground: get_opt("selection_fg")?,
+            author: metadata("author").map(|author| author.to_string()),
+            ..ColorScheme::from_palette(name, ansi, get_color("foreground")?, get_color("background")?)
+        };
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_wezterm(&self) -> String {
+        let res: Vec<String> = self.0.iter().map(|schm| {
+            let color = |c: Color| { toml::Value::String(c.to_hex()) };
+            let palette = |colors: &[Color]| { toml::Value::Array(colors.iter().map(|c| color(*c)).collect()) };
+            let ansi = schm.ansi();
+            let mut colors = toml::value::Table::new();
+            colors.insert("ansi".to_string(), palette(&ansi[..8]));
+            colors.insert("brights".to_string(), palette(&ansi[8..]));
+            colors.insert("foreground".to_string(), color(schm.foreground));
+            colors.insert("background".to_string(), color(schm.background));
+            let cursor = schm.cursor.unwrap_or(schm.foreground);
+            colors.insert("cursor_bg".to_string(), color(cursor));
+            colors.insert("cursor_border".to_string(), color(cursor));
+            colors.insert("cursor_fg".to_string(), color(schm.cursor_text.unwrap_or(schm.background)));
+            colors.insert("selection_bg".to_string(), color(schm.selection_background.unwrap_or(schm.foreground)));
+            colors.insert("selection_fg".to_string(), color(schm.selection_foreground.unwrap_or(schm.background)));
+            let mut metadata = toml::value::Table::new();
+            metadata.insert("name".to_string(), toml::Value::String(schm.name.clone()));
+            if let Some(author) = &schm.author {
+                metadata.insert("author".to_string(), toml::Value::String(author.clone()));
+            }
+            let mut root = toml::value::Table::new();
+            root.insert("colors".to_string(), toml::Value::Table(colors));
+            root.insert("metadata".to_string(), toml::Value::Table(metadata));
+            toml::to_string(&toml::Value::Table(root)).unwrap()
+        }).collect();
+        res.join("")
+    }
+
+    pub fn from_konsole(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let ctx = ErrorContext::new(SchemeFormat::Konsole);
+        let conf = ini::Ini::load_from_str(s).map_err(|e| ini_syntax_error(&ctx, e))?;
+        let name = conf.section(Some("General"))
+            .and_then(|props| props.get("Description"))
+            .unwrap_or("default")
+            .to_string();
+        let ctx = ctx.scheme(&name);
+        // Every color is a section holding a single `Color=r,g,b` entry. `Faint` variants are
+        // read by Konsole but have no counterpart in `ColorScheme`.
+        let get_color = |section: &str| -> Result<Color, SchemeError> {
+            let color = conf.section(Some(section)).and_then(|props| props.get("Color"));
+            ctx.color(&format!("{}.Color", section), color, Color::parse)
+        };
+        let get_opt = |section: &str| -> Result<Option<Color>, SchemeError> {
+            let color = conf.section(Some(section)).and_then(|props| props.get("Color"));
+            ctx.optional_color(&format!("{}.Color", section), color, Color::parse)
+        };
+        let ansi = read_ansi(|i| {
+            if i < 8 { get_color(&format!("Color{}", i)) } else { get_color(&format!("Color{}Intense", i - 8)) }
+        })?;
+        let scheme = ColorScheme {
+            // Konsole draws bold text with the intense foreground
+            bold: get_opt("ForegroundIntense")?,
+            ..ColorScheme::from_palette(name, ansi, get_color("Foreground")?, get_color("Background")?)
+        };
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_konsole(&self) -> String {
+        self.0.iter().map(|schm| {
+            let ansi = schm.ansi();
+            let (normal, intense) = ansi.split_at(8);
+            // Konsole sorts its sections alphabetically; faint colors fall back to the normal ones
+            let mut sections = vec![
+                ("Background".to_string(), schm.background),
+                ("BackgroundFaint".to_string(), schm.background),
+                ("BackgroundIntense".to_string(), schm.background),
+            ];
+            for (i, (n, b)) in normal.iter().zip(intense.iter()).enumerate() {
+                sections.push((format!("Color{}", i), *n));
+                sections.push((format!("Color{}Faint", i), *n));
+                sections.push((format!("Color{}Intense", i), *b));
+            }
+            sections.push(("Foreground".to_string(), schm.foreground));
+            sections.push(("ForegroundFaint".to_string(), schm.foreground));
+            sections.push(("ForegroundIntense".to_string(), schm.bold.unwrap_or(schm.foreground)));
+            let mut res: Vec<String> = sections.iter()
+                .map(|(section, c)| format!("[{}]\nColor={}\n", section, c.to_decimal()))
+                .collect();
+            res.push(format!("[General]\nDescription={}\nOpacity=1\nWallpaper=\n", schm.name));
+            res.join("\n")
+        }).collect::<Vec<String>>().join("\n")
+    }
+
+    pub fn from_xresources(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        let mut name = "default".to_string();
+        let mut macros = HashMap::<&str, &str>::new();
+        let mut kv = HashMap::<&str, &str>::new();
+        for line in s.lines().map(|l| l.trim()) {
+            if let Some(meta) = line.strip_prefix("! name:") {
+                name = meta.trim().to_string();
+                continue;
+            }
+            if let Some(define) = line.strip_prefix("#define") {
+                if let Some((macro_name, value)) = define.trim().split_once(char::is_whitespace) {
+                    macros.insert(macro_name, value.trim());
+                }
+                continue;
+            }
+            // Comments and other preprocessor directives
+            if line.is_empty() || line.starts_with('!') || line.starts_with('#') {
+                continue;
+            }
+            let (resource, value) = match line.split_once(':') {
+                None => { continue; }
+                Some(kv) => { kv }
+            };
+            // `*.color0`, `URxvt.color0`, `XTerm*vt100.color0`... only the last component matters
+            let key = resource.rsplit(['.', '*']).next().unwrap_or(resource).trim();
+            kv.insert(key, value.trim());
+        }
+        let ctx = ErrorContext::new(SchemeFormat::Xresources).scheme(&name);
+        let get_opt = |k: &str| -> Result<Option<Color>, SchemeError> {
+            let mut value = match kv.get(k) {
+                None => { return Ok(None); }
+                Some(value) => { *value }
+            };
+            // Macros may refer to each other, but never loop
+            for _ in 0..macros.len() {
+                match macros.get(value) {
+                    None => { break; }
+                    Some(expanded) => { value = expanded; }
+                }
+            }
+            Color::parse(value).map(Some).ok_or_else(|| ctx.invalid(k, value))
+        };
+        let get_color = |k: &str| -> Result<Color, SchemeError> {
+            get_opt(k)?.ok_or_else(|| ctx.missing(k))
+        };
+        let ansi = read_ansi(|i| get_color(&format!("color{}", i)))?;
+        let scheme = ColorScheme {
+            cursor: get_opt("cursorColor")?,
+            bold: get_opt("colorBD")?,
+            ..ColorScheme::from_palette(name, ansi, get_color("foreground")?, get_color("background")?)
+        };
+        Ok(Box::new(ColorSchemes::new(vec![scheme])))
+    }
+
+    pub fn to_xresources(&self) -> String {
+        self.0.iter().map(|schm| {
+            let mut lines = vec![
+                format!("! name: {}", schm.name),
+                format!("*.foreground: {}", schm.foreground.to_hex()),
+                format!("*.background: {}", schm.background.to_hex()),
+                format!("*.cursorColor: {}", schm.cursor.unwrap_or(schm.foreground).to_hex()),
+            ];
+            if let Some(bold) = schm.bold {
+                lines.push(format!("*.colorBD: {}", bold.to_hex()));
+            }
+            for (i, c) in schm.ansi().iter().enumerate() {
+                lines.push(format!("*.color{}: {}", i, c.to_hex()));
+            }
+            lines.join("\n") + "\n"
+        }).collect::<Vec<String>>().join("\n")
+    }
+
+    pub fn from_gnome_terminal(s: &str) -> Result<Box<ColorSchemes>, SchemeError> {
+        // `dconf dump` writes GKeyFile sections, one `[:uuid]` per profile
+        let mut sections: Vec<(&str, HashMap<&str, &str>)> = Vec::new();
+        for line in s.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push((section, HashMap::new()));
+                continue;
+            }
+            if let (Some((key, value)), Some((_, props))) = (line.split_once('='), sections.last_mut()) {
+                props.insert(key.trim(), value.trim());
+            }
+        }
+        let ctx = ErrorContext::new(SchemeFormat::GnomeTerminal);
+        let mut schemes = Vec::new();
+        for (section, props) in sections {
+            let uuid = match section.strip_prefix(':') {
+                None => { continue; }
+                Some(uuid) => { uuid }
+            };
+            let name = props.get("visible-name").map(|v| unquote_gvariant(v)).unwrap_or_else(|| uuid.to_string());
+            let ctx = ctx.scheme(&name);
+            // Profiles following the system theme carry no palette
+            let palette = match props.get("palette") {
+                None => { continue; }
+                Some(palette) => { palette }
+            };
+            let colors = palette.split('\'')
+                .skip(1)
+                .step_by(2)
+                .map(|c| ctx.color("palette", Some(c), Color::parse))
+                .collect::<Result<Vec<Color>, SchemeError>>()?;
+            let ansi: [Color; 16] = colors.try_into().map_err(|_| ctx.invalid("palette", palette))?;
+            let get_color = |k: &str| -> Result<Color, SchemeError> {
+                ctx.color(k, props.get(k).map(|v| unquote_gvariant(v)).as_deref(), Color::parse)
+            };
+            // Optional colors only apply when their `*-set` / `*-same-as-fg` switch says so
+            let get_opt = |k: &str, enabled: bool| -> Result<Option<Color>, SchemeError> {
+                let value = props.get(k).filter(|_| enabled).map(|v| unquote_gvariant(v));
+                ctx.optional_color(k, value.as_deref(), Color::parse)
+            };
+            let flag = |k: &str| { props.get(k) == Some(&"true") };
+            let cursor_set = flag("cursor-colors-set");
+            let highlight_set = flag("highlight-colors-set");
+            let bold_set = props.get("bold-color-same-as-fg") == Some(&"false");
+            schemes.push(ColorScheme {
+                cursor: get_opt("cursor-background-color", cursor_set)?,
+                cursor_text: get_opt("cursor-foreground-color", cursor_set)?,
+                selection_background: get_opt("highlight-background-color", highlight_set)?,
+                selection_foreground: get_opt("highlight-foreground-color", highlight_set)?,
+                bold: get_opt("bold-color", bold_set)?,
+                ..ColorScheme::from_palette(name, ansi, get_color("foreground-color")?, get_color("background-color")?)
+            });
+        }
+        if schemes.is_empty() {
+            return Err(SchemeError::NoSchemes);
+        }
+        Ok(Box::new(ColorSchemes(schemes)))
+    }
+
+    pub fn to_gnome_terminal(&self) -> String {
+        let rgb = |c: Color| { format!("'rgb({})'", c.to_decimal()) };
+        let mut uuids = Vec::new();
+        let profiles: Vec<String> = self.0.iter().enumerate().map(|(id, schm)| {
+            let uuid = profile_uuid(&schm.name, id);
+            let palette = schm.ansi().iter().map(|c| rgb(*c)).collect::<Vec<String>>().join(", ");
+            let mut optional = Vec::new();
+            if schm.cursor.is_some() || schm.cursor_text.is_some() {
+                optional.push(format!("cursor-background-color={}", rgb(schm.cursor.unwrap_or(schm.foreground))));
+                optional.push("cursor-colors-set=true".to_string());
+                optional.push(format!("cursor-foreground-color={}", rgb(schm.cursor_text.unwrap_or(schm.background))));
+            }
+            if schm.selection_background.is_some() || schm.selection_foreground.is_some() {
+                optional.push(format!("highlight-background-color={}", rgb(schm.selection_background.unwrap_or(schm.foreground))));
+                optional.push("highlight-colors-set=true".to_string());
+                optional.push(format!("highlight-foreground-color={}", rgb(schm.selection_foreground.unwrap_or(schm.background))));
+            }
+            let profile = format!("[:{uuid}]
+background-color={background}
+{bold}
+{optional}foreground-color={foreground}
+palette=[{palette}]
+use-theme-colors=false
+visible-name='{name}'
+",
+                    uuid = uuid,
+                    background = rgb(schm.background),
+                    bold = match schm.bold {
+                        None => { "bold-color-same-as-fg=true".to_string() }
+                        Some(bold) => { format!("bold-color={}\nbold-color-same-as-fg=false", rgb(bold)) }
+                    },
+                    optional = optional.iter().map(|l| format!("{}\n", l)).collect::<String>(),
+                    foreground = rgb(schm.foreground),
+                    palette = palette,
+                    name = schm.name.replace('\\', "\\\\").replace('\'', "\\'"),
+            );
+            uuids.push(format!("'{}'", uuid));
+            profile
+        }).collect();
+        // Loading `[/]` would replace the existing profile list, so only hint at it
+        format!("# dconf load /org/gnome/terminal/legacy/profiles:/ < this file, then append
+# {} to the `list` key to show the profiles
+{}", uuids.join(", "), profiles.join("\n"))
     }
 
     pub fn from_literal(s: &str, fmt: SchemeFormat) -> Result<Box<ColorSchemes>, SchemeError> {
-        match fmt {
-            SchemeFormat::WindowsTerminal => { ColorSchemes::from_wt(s) }
-            SchemeFormat::XShell => { ColorSchemes::from_xshell(s) }
-            SchemeFormat::Alacritty => { ColorSchemes::from_alacritty(s) }
-            _ => { Err(SchemeError::Unsupported) }
+        fmt.info().reader.read(s)
+    }
+
+    /// Fails with `MultipleSchemes` when `fmt` holds a single scheme per file and there are several
+    pub fn to_literal(&self, fmt: SchemeFormat) -> Result<String, SchemeError> {
+        let info = fmt.info();
+        if !info.capabilities.multiple_schemes && self.0.len() > 1 {
+            return Err(SchemeError::MultipleSchemes { format: fmt, count: self.0.len() });
         }
+        Ok(info.writer.write(self))
+    }
+
+    /// Write every scheme on its own, returning `(file name, content)` pairs.
+    /// File names are derived from the scheme names and made unique.
+    pub fn to_literal_per_scheme(&self, fmt: SchemeFormat) -> Vec<(String, String)> {
+        let info = fmt.info();
+        let ext = info.output_extension;
+        let mut used = Vec::<String>::new();
+        self.0.iter().map(|schm| {
+            let stem = sanitize_file_name(&schm.name);
+            let mut file_name = format!("{}.{}", stem, ext);
+            for n in 2.. {
+                if !used.iter().any(|f| f.eq_ignore_ascii_case(&file_name)) {
+                    break;
+                }
+                file_name = format!("{}-{}.{}", stem, n, ext);
+            }
+            used.push(file_name.clone());
+            (file_name, info.writer.write(&ColorSchemes(vec![schm.clone()])))
+        }).collect()
+    }
+}
+
+/// Look up a profile by name or guid in `profiles.list` (or the legacy `profiles` array).
+/// `defaults` addresses `profiles.defaults`.
+fn find_wt_profile<'a>(root: &'a Node, profile: &str) -> Option<&'a Node> {
+    let profiles = root.get("profiles")?;
+    if profile == "defaults" {
+        return profiles.get("defaults").filter(|d| matches!(d.kind, NodeKind::Object(_)));
     }
+    let list = match profiles.kind {
+        NodeKind::Array(_) => { profiles }
+        _ => { profiles.get("list")? }
+    };
+    list.items().iter().find(|p| {
+        ["name", "guid"].iter().any(|k| p.get(k).and_then(Node::as_str) == Some(profile))
+    })
+}
+
+/// Replace the characters that are not allowed in file names on Windows or Unix
+fn sanitize_file_name(name: &str) -> String {
+    let name: String = name.trim()
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '_' } else { c })
+        .collect();
+    if name.is_empty() || name.chars().all(|c| c == '.') { "scheme".to_string() } else { name }
+}
+
+/// Strip the quotes of a GVariant string and undo its escapes
+fn unquote_gvariant(s: &str) -> String {
+    let s = s.trim();
+    let s = s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')).unwrap_or(s);
+    s.replace("\\'", "'").replace("\\\\", "\\")
+}
 
-    pub fn to_literal(&self, fmt: SchemeFormat) -> Box<String> {
-        match fmt {
-            SchemeFormat::WindowsTerminal => { self.to_wt() }
-            SchemeFormat::SecureCRT => { unimplemented!() }
-            SchemeFormat::XShell => { self.to_xshell() }
-            SchemeFormat::Alacritty => { self.to_alacritty() }
-            SchemeFormat::MobaXTerm => { unimplemented!() }
+/// Stable profile id derived from the scheme name, so converting twice yields the same profiles
+fn profile_uuid(name: &str, id: usize) -> String {
+    let fnv = |seed: u64| {
+        format!("{}\0{}", name, id).bytes()
+            .fold(seed, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+    };
+    let (hi, lo) = (fnv(0xcbf29ce484222325), fnv(0x84222325cbf29ce4));
+    format!("{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+            hi >> 32, (hi >> 16) & 0xffff, hi & 0xfff, 0x8000 | (lo >> 48) & 0x3fff, lo & 0xffffffffffff)
+}
+
+/// Keys of the `normal` and `bright` tables of Alacritty, in ANSI order
+const ALACRITTY_COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Map the `colors` section of an Alacritty configuration, `get(table, key)` returning the
+/// value of `colors.<table>.<key>` in either YAML or TOML
+fn read_alacritty<'a>(ctx: &ErrorContext, get: impl Fn(&str, &str) -> Option<&'a str>) -> Result<ColorScheme, SchemeError> {
+    let get_color = |typ: &str, k: &str| {
+        ctx.color(&format!("colors.{}.{}", typ, k), get(typ, k), Color::parse)
+    };
+    let get_opt = |typ: &str, k: &str| {
+        let color_str = get(typ, k).filter(|v| !is_alacritty_cell_color(v));
+        ctx.optional_color(&format!("colors.{}.{}", typ, k), color_str, Color::parse)
+    };
+    let ansi = read_ansi(|i| {
+        if i < 8 { get_color("normal", ALACRITTY_COLORS[i]) } else { get_color("bright", ALACRITTY_COLORS[i - 8]) }
+    })?;
+    Ok(ColorScheme {
+        cursor: get_opt("cursor", "cursor")?,
+        cursor_text: get_opt("cursor", "text")?,
+        selection_background: get_opt("selection", "background")?,
+        selection_foreground: get_opt("selection", "text")?,
+        bold: get_opt("primary", "bright_foreground")?,
+        ..ColorScheme::from_palette("default".to_string(), ansi, get_color("primary", "foreground")?, get_color("primary", "background")?)
+    })
+}
+
+/// Tables of the Alacritty `colors` section with their `(key, color)` entries, for the YAML and TOML writers
+fn alacritty_tables(schm: &ColorScheme) -> Vec<(&'static str, Vec<(&'static str, Color)>)> {
+    let mut primary = vec![("foreground", schm.foreground), ("background", schm.background)];
+    primary.extend(schm.bold.map(|c| ("bright_foreground", c)));
+    let mut tables = vec![("primary", primary)];
+    // Alacritty falls back to the cell colors by itself
+    let optional = |colors: &[(&'static str, Option<Color>)]| -> Vec<(&'static str, Color)> {
+        colors.iter().filter_map(|(k, c)| c.map(|c| (*k, c))).collect()
+    };
+    for (table, colors) in [
+        ("cursor", optional(&[("text", schm.cursor_text), ("cursor", schm.cursor)])),
+        ("selection", optional(&[("text", schm.selection_foreground), ("background", schm.selection_background)])),
+    ] {
+        if !colors.is_empty() {
+            tables.push((table, colors));
         }
     }
+    let ansi = schm.ansi();
+    tables.push(("normal", ALACRITTY_COLORS.iter().copied().zip(ansi[..8].iter().copied()).collect()));
+    tables.push(("bright", ALACRITTY_COLORS.iter().copied().zip(ansi[8..].iter().copied()).collect()));
+    tables
 }
 
-trait AsColor {
-    fn to_hex_repr(&self) -> String;
+/// `CellForeground` / `CellBackground` make Alacritty reuse the colors of the cell
+fn is_alacritty_cell_color(s: &str) -> bool {
+    matches!(s, "CellForeground" | "CellBackground")
 }
 
-impl AsColor for RGBColor {
-    fn to_hex_repr(&self) -> String {
-        format!("#{:06X}", self)
+/// Read the 16 ANSI colors, `get` being called with indexes 0 (black) to 15 (bright white)
+fn read_ansi(mut get: impl FnMut(usize) -> Result<Color, SchemeError>) -> Result<[Color; 16], SchemeError> {
+    let mut ansi = [Color::rgb(0, 0, 0); 16];
+    for (i, color) in ansi.iter_mut().enumerate() {
+        *color = get(i)?;
     }
-}
\ No newline at end of file
+    Ok(ansi)
+}
+
+/// Attach the position reported by the TOML parser to a syntax error
+fn toml_syntax_error(ctx: &ErrorContext, e: toml::de::Error) -> SchemeError {
+    let location = e.line_col().map(|(line, column)| Location { line: line + 1, column: column + 1 });
+    ctx.syntax(&e, location)
+}
+
+/// Scheme names of the XShell `Names` section in file order, from `name{count-1}` down to `name0`.
+/// `None` when the section is absent, or its count or one of the names is missing.
+fn xshell_names(conf: &ini::Ini) -> Option<Vec<String>> {
+    let names = conf.iter()
+        .find(|(name, _)| name.is_some_and(|name| name.eq_ignore_ascii_case("Names")))?
+        .1;
+    let count: usize = names.get("count")?.trim().parse().ok()?;
+    (0..count).rev()
+        .map(|id| names.get(format!("name{}", id)).map(|name| name.to_string()))
+        .collect()
+}
+
+/// Attach the position reported by the INI parser to a syntax error
+fn ini_syntax_error(ctx: &ErrorContext, e: ini::ParseError) -> SchemeError {
+    ctx.syntax(&e.msg, Some(Location { line: e.line + 1, column: e.col.max(1) }))
+}
+
+impl ColorScheme {
+    /// The 16 ANSI colors, black to bright white
+    pub fn ansi(&self) -> [Color; 16] {
+        [
+            self.black, self.red, self.green, self.yellow,
+            self.blue, self.magenta, self.cyan, self.white,
+            self.bright_black, self.bright_red, self.bright_green, self.bright_yellow,
+            self.bright_blue, self.bright_magenta, self.bright_cyan, self.bright_white,
+        ]
+    }
+
+    fn to_wt_value(&self) -> Value {
+        let mut bt = serde_json::Map::<String, Value>::new();
+        bt.insert("name".to_string(), Value::String(self.name.clone()));
+        bt.insert("background".to_string(), Value::String(self.background.to_hex()));
+        bt.insert("black".to_string(), Value::String(self.black.to_hex()));
+        bt.insert("blue".to_string(), Value::String(self.blue.to_hex()));
+        bt.insert("brightBlack".to_string(), Value::String(self.bright_black.to_hex()));
+        bt.insert("brightBlue".to_string(), Value::String(self.bright_blue.to_hex()));
+        bt.insert("brightCyan".to_string(), Value::String(self.bright_cyan.to_hex()));
+        bt.insert("brightGreen".to_string(), Value::String(self.bright_green.to_hex()));
+        bt.insert("brightPurple".to_string(), Value::String(self.bright_magenta.to_hex()));
+        bt.insert("brightRed".to_string(), Value::String(self.bright_red.to_hex()));
+        bt.insert("brightWhite".to_string(), Value::String(self.bright_white.to_hex()));
+        bt.insert("brightYellow".to_string(), Value::String(self.bright_yellow.to_hex()));
+        bt.insert("cursorColor".to_string(), Value::String(self.cursor.unwrap_or(self.foreground).to_hex()));
+        bt.insert("cyan".to_string(), Value::String(self.cyan.to_hex()));
+        bt.insert("foreground".to_string(), Value::String(self.foreground.to_hex()));
+        bt.insert("green".to_string(), Value::String(self.green.to_hex()));
+        bt.insert("purple".to_string(), Value::String(self.magenta.to_hex()));
+        bt.insert("red".to_string(), Value::String(self.red.to_hex()));
+        bt.insert("selectionBackground".to_string(), Value::String(self.selection_background.unwrap_or(self.foreground).to_hex()));
+        bt.insert("white".to_string(), Value::String(self.white.to_hex()));
+        bt.insert("yellow".to_string(), Value::String(self.yellow.to_hex()));
+        Value::Object(bt)
+    }
+
+    /// Scheme made of the 16 ANSI colors, black to bright white, without the optional colors
+    pub fn from_palette(name: String, ansi: [Color; 16], foreground: Color, background: Color) -> ColorScheme {
+        ColorScheme {
+            name,
+            author: None,
+            black: ansi[0],
+            red: ansi[1],
+            green: ansi[2],
+            yellow: ansi[3],
+            blue: ansi[4],
+            magenta: ansi[5],
+            cyan: ansi[6],
+            white: ansi[7],
+            bright_black: ansi[8],
+            bright_red: ansi[9],
+            bright_green: ansi[10],
+            bright_yellow: ansi[11],
+            bright_blue: ansi[12],
+            bright_magenta: ansi[13],
+            bright_cyan: ansi[14],
+            bright_white: ansi[15],
+            background,
+            foreground,
+            cursor: None,
+            cursor_text: None,
+            selection_background: None,
+            selection_foreground: None,
+            bold: None,
+        }
+    }
+}
+
+enum SecureCRTValue {
+    /// Raw value of `S:` strings and `D:` dwords
+    Str(String),
+    /// Raw bytes, colors being stored as `R G B 00` quads
+    Blob(Vec<u8>),
+}
+
+/// Parse `S:"key"=str`, `D:"key"=dword` and `B:"key"=len` entries. Blob bytes follow on
+/// space-indented continuation lines.
+fn parse_securecrt_entries(ctx: &ErrorContext, s: &str) -> Result<Vec<(char, String, SecureCRTValue)>, SchemeError> {
+    let mut entries = Vec::new();
+    let mut lines = s.lines().enumerate().peekable();
+    let at_line = |n: usize| { Some(Location { line: n + 1, column: 1 }) };
+    while let Some((n, line)) = lines.next() {
+        let line = line.trim_end();
+        let typ = match line.chars().next() {
+            Some(c @ ('S' | 'D' | 'B')) if line[1..].starts_with(":\"") => { c }
+            _ => { continue; }
+        };
+        let (key, value) = match line[3..].split_once("\"=") {
+            None => { return Err(ctx.syntax("expected `\"=` after the key", at_line(n))); }
+            Some(kv) => { kv }
+        };
+        let value = match typ {
+            'S' | 'D' => { SecureCRTValue::Str(value.to_string()) }
+            _ => {
+                let len = usize::from_str_radix(value, 16)
+                    .map_err(|_| ctx.syntax(format!("invalid blob length `{}`", value), at_line(n)))?;
+                // No preallocation, `len` comes from the file
+                let mut bytes = Vec::new();
+                while bytes.len() < len {
+                    let (m, cont) = match lines.next_if(|(_, l)| l.starts_with(' ')) {
+                        None => { return Err(ctx.syntax(format!("blob `{}` is shorter than {} bytes", key, len), at_line(n))); }
+                        Some(l) => { l }
+                    };
+                    for byte in cont.split_whitespace() {
+                        bytes.push(u8::from_str_radix(byte, 16)
+                            .map_err(|_| ctx.syntax(format!("invalid byte `{}`", byte), at_line(m)))?);
+                    }
+                }
+                SecureCRTValue::Blob(bytes)
+            }
+        };
+        entries.push((typ, key.to_string(), value));
+    }
+    Ok(entries)
+}
+
+/// Colors of a blob made of `R G B 00` quads
+fn securecrt_colors(bytes: &[u8]) -> Vec<Color> {
+    bytes.chunks(4)
+        .map(|quad| {
+            let byte = |i: usize| { quad.get(i).copied().unwrap_or(0) };
+            Color::rgb(byte(0), byte(1), byte(2))
+        })
+        .collect()
+}
+
+fn securecrt_blob_line(colors: &[Color]) -> String {
+    colors.iter()
+        .map(|c| format!(" {:02x} {:02x} {:02x} 00", c.r, c.g, c.b))
+        .collect()
+}
@@ -0,0 +1,322 @@
+use serde::Serialize;
+use serde_json::{Error, Value};
+use crate::profile::error::{ErrorContext, Location, SchemeError};
+
+/// Parse JSON with comments and trailing commas, as found in Windows Terminal settings
+pub(crate) fn parse_jsonc(ctx: &ErrorContext, s: &str) -> Result<Value, SchemeError> {
+    serde_json::from_str(&strip_jsonc(s))
+        .map_err(|e: Error| ctx.syntax(&e, Some(Location { line: e.line(), column: e.column() })))
+}
+
+/// Blank out `//` and `/* */` comments and trailing commas with spaces, keeping line breaks and
+/// byte offsets so that positions in the result are positions in the input
+pub(crate) fn strip_jsonc(s: &str) -> String {
+    let blank = |out: &mut String, c: char| {
+        for _ in 0..c.len_utf8() {
+            out.push(' ');
+        }
+    };
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    let mut in_string = false;
+    // Position in `out` of a comma that is only followed by blanks so far
+    let mut pending_comma: Option<usize> = None;
+    // Last character outside of strings and comments, a comma must follow a value to be trailing
+    let mut last = ' ';
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => { out.extend(chars.next()); }
+                '"' => { in_string = false; }
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    blank(&mut out, c);
+                }
+                out.push(' ');
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                out.push_str("  ");
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    match c {
+                        '\n' => { out.push('\n'); }
+                        c => { blank(&mut out, c); }
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            (c, _) if c.is_whitespace() => { out.push(c); }
+            (c, _) => {
+                if let Some(comma) = pending_comma.take() {
+                    if c == '}' || c == ']' {
+                        out.replace_range(comma..comma + 1, " ");
+                    }
+                }
+                match c {
+                    ',' if !matches!(last, '[' | '{' | ',') => { pending_comma = Some(out.len()); }
+                    '"' => { in_string = true; }
+                    _ => {}
+                }
+                last = c;
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+/// Value of a JSONC document with its byte range in the text, to edit the text in place
+#[derive(Debug)]
+pub(crate) struct Node {
+    pub start: usize,
+    pub end: usize,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug)]
+pub(crate) enum NodeKind {
+    Object(Vec<Member>),
+    Array(Vec<Node>),
+    String(String),
+    /// Numbers, booleans and null
+    Scalar,
+}
+
+#[derive(Debug)]
+pub(crate) struct Member {
+    pub key: String,
+    /// Offset of the opening quote of the key
+    pub start: usize,
+    pub value: Node,
+}
+
+impl Node {
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.members().iter().rev().find(|m| m.key == key).map(|m| &m.value)
+    }
+
+    /// Members of an object, nothing for other values
+    pub fn members(&self) -> &[Member] {
+        match &self.kind {
+            NodeKind::Object(members) => { members }
+            _ => { &[] }
+        }
+    }
+
+    /// Items of an array, nothing for other values
+    pub fn items(&self) -> &[Node] {
+        match &self.kind {
+            NodeKind::Array(items) => { items }
+            _ => { &[] }
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::String(s) => { Some(s) }
+            _ => { None }
+        }
+    }
+}
+
+/// Locate the values of a document that `parse_jsonc` accepts
+pub(crate) fn scan(s: &str) -> Node {
+    let stripped = strip_jsonc(s);
+    let mut scanner = Scanner { s: stripped.as_bytes(), pos: 0 };
+    scanner.value()
+}
+
+struct Scanner<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&mut self) -> Option<u8> {
+        while self.s.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        self.s.get(self.pos).copied()
+    }
+
+    fn value(&mut self) -> Node {
+        let start = self.peek().map(|_| self.pos).unwrap_or(self.s.len());
+        let kind = match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                loop {
+                    match self.peek() {
+                        None => { break; }
+                        Some(b'}') => { self.pos += 1; break; }
+                        Some(b',') => { self.pos += 1; }
+                        Some(_) => {
+                            let key = self.value();
+                            if self.peek() == Some(b':') {
+                                self.pos += 1;
+                            }
+                            let value = self.value();
+                            members.push(Member { key: key.as_str().unwrap_or_default().to_string(), start: key.start, value });
+                        }
+                    }
+                }
+                NodeKind::Object(members)
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    match self.peek() {
+                        None => { break; }
+                        Some(b']') => { self.pos += 1; break; }
+                        Some(b',') => { self.pos += 1; }
+                        Some(_) => { items.push(self.value()); }
+                    }
+                }
+                NodeKind::Array(items)
+            }
+            Some(b'"') => {
+                self.pos += 1;
+                while let Some(&b) = self.s.get(self.pos) {
+                    self.pos += 1;
+                    match b {
+                        b'\\' => { self.pos += 1; }
+                        b'"' => { break; }
+                        _ => {}
+                    }
+                }
+                let literal = std::str::from_utf8(&self.s[start..self.pos.min(self.s.len())]).unwrap_or_default();
+                NodeKind::String(serde_json::from_str(literal).unwrap_or_default())
+            }
+            _ => {
+                // Always move on, even on a stray `:` or `}`
+                self.pos += 1;
+                while self.s.get(self.pos).is_some_and(|b| !b",:]}".contains(b) && !b.is_ascii_whitespace()) {
+                    self.pos += 1;
+                }
+                NodeKind::Scalar
+            }
+        };
+        Node { start, end: self.pos.min(self.s.len()), kind }
+    }
+}
+
+/// Replacements and insertions in a JSONC document, laid out like the surrounding lines so that
+/// everything else, comments included, is left untouched
+pub(crate) struct Edits<'a> {
+    text: &'a str,
+    newline: &'static str,
+    /// Indentation of one level, that of the first indented line
+    unit: String,
+    edits: Vec<(usize, usize, String)>,
+}
+
+impl<'a> Edits<'a> {
+    pub fn new(text: &'a str) -> Edits<'a> {
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+        let unit = text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("    ")
+            .to_string();
+        Edits { text, newline, unit, edits: Vec::new() }
+    }
+
+    /// Replace a value
+    pub fn replace(&mut self, node: &Node, value: &Value) {
+        let indent = self.line_indent(node.start);
+        let text = self.pretty(value, &indent);
+        self.edits.push((node.start, node.end, text));
+    }
+
+    /// Add members at the end of an object, or items without key at the end of an array
+    pub fn append(&mut self, node: &Node, entries: &[(Option<&str>, Value)]) {
+        if entries.is_empty() {
+            return;
+        }
+        let last = match &node.kind {
+            NodeKind::Object(members) => { members.last().map(|m| (m.start, m.value.end)) }
+            NodeKind::Array(items) => { items.last().map(|item| (item.start, item.end)) }
+            _ => { return; }
+        };
+        let newline = self.newline;
+        match last {
+            Some((start, end)) => {
+                let indent = self.line_indent(start);
+                let separator = match self.starts_line(start) {
+                    true => { format!(",{}{}", newline, indent) }
+                    false => { ", ".to_string() }
+                };
+                let text: String = entries.iter()
+                    .map(|entry| format!("{}{}", separator, self.entry(entry, &indent)))
+                    .collect();
+                self.edits.push((end, end, text));
+            }
+            None => {
+                let indent = self.line_indent(node.start);
+                let inner = format!("{}{}", indent, self.unit);
+                let body = entries.iter()
+                    .map(|entry| self.entry(entry, &inner))
+                    .collect::<Vec<String>>()
+                    .join(&format!(",{}{}", newline, inner));
+                let (open, close) = (node.start + 1, node.end - 1);
+                match self.text[open..close].trim().is_empty() {
+                    true => { self.edits.push((open, close, format!("{}{}{}{}{}", newline, inner, body, newline, indent))); }
+                    // Only comments inside, keep them after the new entries
+                    false => { self.edits.push((open, open, format!("{}{}{}", newline, inner, body))); }
+                }
+            }
+        }
+    }
+
+    /// Text with the edits made, from the last one so that offsets stay valid
+    pub fn apply(mut self) -> String {
+        self.edits.sort_by_key(|(start, _, _)| *start);
+        let mut text = self.text.to_string();
+        for (start, end, replacement) in self.edits.iter().rev() {
+            text.replace_range(*start..*end, replacement);
+        }
+        text
+    }
+
+    fn entry(&self, (key, value): &(Option<&str>, Value), indent: &str) -> String {
+        match key {
+            Some(key) => { format!("{}: {}", Value::String(key.to_string()), self.pretty(value, indent)) }
+            None => { self.pretty(value, indent) }
+        }
+    }
+
+    /// Pretty JSON whose lines after the first start with `indent`
+    fn pretty(&self, value: &Value, indent: &str) -> String {
+        let mut buf = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(self.unit.as_bytes());
+        let mut ser = serde_json::Serializer::with_formatter(&mut buf, formatter);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(buf).unwrap().replace('\n', &format!("{}{}", self.newline, indent))
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Leading blanks of the line holding `pos`
+    fn line_indent(&self, pos: usize) -> String {
+        let line = &self.text[self.line_start(pos)..pos];
+        line[..line.len() - line.trim_start().len()].to_string()
+    }
+
+    fn starts_line(&self, pos: usize) -> bool {
+        self.text[self.line_start(pos)..pos].trim().is_empty()
+    }
+}
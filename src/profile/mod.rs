@@ -1 +1,8 @@
-pub(crate) mod generic;
\ No newline at end of file
+pub(crate) mod color;
+pub(crate) mod detect;
+pub(crate) mod encoding;
+pub(crate) mod error;
+pub(crate) mod format;
+pub(crate) mod generic;
+pub(crate) mod jsonc;
+pub(crate) mod select;
@@ -0,0 +1,39 @@
+use regex::Regex;
+use crate::profile::error::SchemeError;
+
+/// Matches scheme names.
+/// `/…/` is a regex, a pattern containing `*` or `?` is a glob, anything else an exact name.
+#[derive(Debug, Clone)]
+pub enum SchemePattern {
+    Exact(String),
+    /// Glob compiled into an anchored regex
+    Glob(Regex),
+    Regex(Regex),
+}
+
+impl SchemePattern {
+    pub fn parse(s: &str) -> Result<SchemePattern, SchemeError> {
+        let invalid = |e: regex::Error| SchemeError::InvalidArgument { value: s.to_string(), message: e.to_string() };
+        if let Some(re) = s.strip_prefix('/').and_then(|s| s.strip_suffix('/')).filter(|re| !re.is_empty()) {
+            return Regex::new(re).map(SchemePattern::Regex).map_err(invalid);
+        }
+        if s.contains(['*', '?']) {
+            let re: String = s.chars()
+                .map(|c| match c {
+                    '*' => { ".*".to_string() }
+                    '?' => { ".".to_string() }
+                    c => { regex::escape(&c.to_string()) }
+                })
+                .collect();
+            return Regex::new(&format!("^{}$", re)).map(SchemePattern::Glob).map_err(invalid);
+        }
+        Ok(SchemePattern::Exact(s.to_string()))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            SchemePattern::Exact(exact) => { exact == name }
+            SchemePattern::Glob(re) | SchemePattern::Regex(re) => { re.is_match(name) }
+        }
+    }
+}